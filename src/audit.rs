@@ -0,0 +1,237 @@
+use crate::{
+    colors::{self, RGBA},
+    ir::{PaletteColor, ResolvedTheme},
+};
+
+/// Minimum contrasts a group must have against its background
+#[derive(Debug, Copy, Clone)]
+pub struct Thresholds {
+    /// WCAG 2 contrast ratio, from 1 to 21
+    pub wcag: f64,
+    /// Absolute APCA lightness contrast (Lc), from 0 to about 108
+    pub apca: f64,
+}
+
+impl Default for Thresholds {
+    fn default() -> Thresholds {
+        // The minimums both guidelines recommend for body text
+        Thresholds {
+            wcag: 4.5,
+            apca: 60.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Finding {
+    pub group: String,
+    pub foreground: String,
+    pub background: String,
+    pub wcag: f64,
+    pub apca: f64,
+}
+
+impl Finding {
+    pub fn passes(&self, thresholds: &Thresholds) -> bool {
+        self.wcag >= thresholds.wcag && self.apca.abs() >= thresholds.apca
+    }
+}
+
+/// Computes the contrast of every highlighted group against its effective
+/// background, which is `Normal`'s one when the group doesn't set it.
+pub fn audit(theme: &ResolvedTheme) -> Vec<Finding> {
+    let (normal_fg, normal_bg) = theme.normal_colors();
+    let mut findings: Vec<Finding> = Vec::new();
+
+    for highlight in theme.highlights.iter().rev() {
+        let group = &highlight.group;
+        // Linked groups are audited through their target
+        if findings.iter().any(|finding| &finding.group == group)
+            || theme.links.iter().any(|link| &link.group == group)
+        {
+            continue;
+        }
+
+        let color = |name: &Option<String>| name.as_ref().and_then(|name| theme.color(name));
+        let foreground = color(&highlight.foreground).unwrap_or(&normal_fg);
+        let background = color(&highlight.background).unwrap_or(&normal_bg);
+
+        if let (Ok(fg), Ok(bg)) = (
+            colors::from_hex_string(foreground),
+            colors::from_hex_string(background),
+        ) {
+            findings.push(Finding {
+                group: group.to_string(),
+                foreground: foreground.to_string(),
+                background: background.to_string(),
+                wcag: colors::contrast_ratio(fg, bg),
+                apca: colors::apca_contrast(fg, bg),
+            });
+        }
+    }
+
+    findings.reverse();
+    findings
+}
+
+/// Adjusts the lightness of the groups below `thresholds` in OKLCH, keeping
+/// their hue and chroma. The group's own foreground is adjusted when it has
+/// one, otherwise its background is. Returns the groups that were changed.
+pub fn fix(theme: &mut ResolvedTheme, thresholds: &Thresholds) -> Vec<String> {
+    let mut fixed = Vec::new();
+
+    for finding in audit(theme) {
+        if finding.passes(thresholds) {
+            continue;
+        }
+
+        let (fg, bg) = match (
+            colors::from_hex_string(&finding.foreground),
+            colors::from_hex_string(&finding.background),
+        ) {
+            (Ok(fg), Ok(bg)) => (fg, bg),
+            _ => continue,
+        };
+
+        let index = match theme
+            .highlights
+            .iter()
+            .rposition(|highlight| highlight.group == finding.group)
+        {
+            Some(index) => index,
+            None => continue,
+        };
+
+        if theme.highlights[index].foreground.is_some() {
+            let adjusted = adjust(fg, bg, thresholds, true);
+            let name = palette_entry(theme, adjusted);
+            theme.highlights[index].foreground = Some(name);
+        } else {
+            let adjusted = adjust(bg, fg, thresholds, false);
+            let name = palette_entry(theme, adjusted);
+            theme.highlights[index].background = Some(name);
+        }
+        fixed.push(finding.group);
+    }
+
+    fixed
+}
+
+/// Moves the lightness of `color` away from `other` until both contrasts
+/// pass, or until it can't get any lighter or darker.
+fn adjust(color: RGBA, other: RGBA, thresholds: &Thresholds, is_foreground: bool) -> RGBA {
+    let mut oklch = colors::to_oklch(color);
+    let step = if colors::is_light(other) { -0.01 } else { 0.01 };
+
+    loop {
+        let candidate = colors::from_oklch(oklch);
+        let (fg, bg) = if is_foreground {
+            (candidate, other)
+        } else {
+            (other, candidate)
+        };
+        let passes = colors::contrast_ratio(fg, bg) >= thresholds.wcag
+            && colors::apca_contrast(fg, bg).abs() >= thresholds.apca;
+
+        if passes || oklch.l <= 0.0 || oklch.l >= 1.0 {
+            return candidate;
+        }
+        oklch.l = (oklch.l + step).clamp(0.0, 1.0);
+    }
+}
+
+/// Returns the name of the palette entry with `color`, creating it if needed
+fn palette_entry(theme: &mut ResolvedTheme, color: RGBA) -> String {
+    let hex = colors::to_rgb_hex_string(color);
+    if let Some(entry) = theme.palette.iter().find(|entry| entry.color == hex) {
+        return entry.name.clone();
+    }
+
+    // Looked up in the whole palette, as `NONE` entries have names too
+    let taken = |name: &str| theme.palette.iter().any(|entry| entry.name == name);
+    let mut idx = theme.palette.len();
+    while taken(&format!("Color{}", idx)) {
+        idx += 1;
+    }
+    let name = format!("Color{}", idx);
+    theme.palette.push(PaletteColor {
+        name: name.clone(),
+        color: hex,
+    });
+    name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::theme::Theme;
+
+    const FIXTURE: &str = r##"{
+        "colors": {
+            "editor.background": "#1e1e1e",
+            "editor.foreground": "#d4d4d4"
+        },
+        "tokenColors": [
+            { "scope": "comment", "settings": { "foreground": "#333333" } },
+            { "scope": "keyword", "settings": { "foreground": "#ffffff" } }
+        ]
+    }"##;
+
+    fn finding<'a>(findings: &'a [Finding], group: &str) -> &'a Finding {
+        findings
+            .iter()
+            .find(|finding| finding.group == group)
+            .unwrap()
+    }
+
+    #[test]
+    fn can_audit_contrast() {
+        let theme: Theme = FIXTURE.parse().unwrap();
+        let findings = audit(&theme.resolve());
+        let thresholds = Thresholds::default();
+
+        let comment = finding(&findings, "Comment");
+        assert_eq!(comment.foreground, "#333333");
+        assert_eq!(comment.background, "#1e1e1e");
+        assert!(!comment.passes(&thresholds));
+
+        let keyword = finding(&findings, "Keyword");
+        assert!(keyword.wcag > 15.0);
+        assert!(keyword.passes(&thresholds));
+    }
+
+    #[test]
+    fn can_fix_contrast() {
+        let theme: Theme = FIXTURE.parse().unwrap();
+        let mut resolved = theme.resolve();
+        let thresholds = Thresholds::default();
+
+        let fixed = fix(&mut resolved, &thresholds);
+        assert!(fixed.contains(&"Comment".to_string()));
+        assert!(!fixed.contains(&"Keyword".to_string()));
+
+        let findings = audit(&resolved);
+        let comment = finding(&findings, "Comment");
+        assert!(comment.passes(&thresholds));
+        // Only the lightness is raised, the gray stays gray
+        let fg = colors::from_hex_string(&comment.foreground).unwrap();
+        let oklch = colors::to_oklch(fg);
+        assert!(oklch.l > colors::to_oklch(colors::from_hex_string("#333333").unwrap()).l);
+        assert!(oklch.c < 0.01);
+        assert!(findings.iter().all(|finding| finding.passes(&thresholds)));
+    }
+
+    #[test]
+    fn can_add_palette_entries_next_to_none() {
+        let mut theme = ResolvedTheme::default();
+        for (name, color) in &[("Color0", "#000000"), ("Color2", "NONE")] {
+            theme.palette.push(PaletteColor {
+                name: name.to_string(),
+                color: color.to_string(),
+            });
+        }
+
+        let name = palette_entry(&mut theme, colors::from_hex_string("#ffffff").unwrap());
+        assert_eq!(name, "Color3");
+    }
+}
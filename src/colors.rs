@@ -1,41 +1,190 @@
-pub fn from_hex_string(hex: &str) -> Result<RGBA, String> {
-    match hex.len() {
-        4 => Ok(RGBA {
-            r: hex_to_u8(&hex[1..2].repeat(2)),
-            g: hex_to_u8(&hex[2..3].repeat(2)),
-            b: hex_to_u8(&hex[3..4].repeat(2)),
-            a: 1.0,
-        }),
-        5 => Ok(RGBA {
-            r: hex_to_u8(&hex[1..2].repeat(2)),
-            g: hex_to_u8(&hex[2..3].repeat(2)),
-            b: hex_to_u8(&hex[3..4].repeat(2)),
-            a: hex_to_u8(&hex[4..5].repeat(2)) as f32 / 255.0,
-        }),
-        7 => Ok(RGBA {
-            r: hex_to_u8(&hex[1..3]),
-            g: hex_to_u8(&hex[3..5]),
-            b: hex_to_u8(&hex[5..7]),
-            a: 1.0,
+use std::fmt;
+
+use serde::Serialize;
+
+mod named;
+
+pub use named::NAMED_COLORS;
+
+/// Why a color could not be parsed
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub enum ColorError {
+    Empty,
+    /// Not a `#rgb`, `#rgba`, `#rrggbb` or `#rrggbbaa` hex string
+    InvalidHex(String),
+    /// A malformed `rgb()`, `rgba()`, `hsl()` or `hsla()` color
+    InvalidFunction(String),
+    /// Neither a hex string, a color function nor a CSS named color
+    Unknown(String),
+}
+
+impl fmt::Display for ColorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ColorError::Empty => write!(f, "Empty color"),
+            ColorError::InvalidHex(color) => write!(f, "Invalid hex color: {}", color),
+            ColorError::InvalidFunction(color) => write!(f, "Invalid color function: {}", color),
+            ColorError::Unknown(color) => write!(f, "Unknown color: {}", color),
+        }
+    }
+}
+
+impl std::error::Error for ColorError {}
+
+/// Parses any color format found in VSCode themes: hex strings, CSS
+/// `rgb()`/`rgba()`/`hsl()`/`hsla()` functions, CSS named colors and
+/// `transparent`, in any case and surrounded by whitespace.
+pub fn parse(color: &str) -> Result<RGBA, ColorError> {
+    let color = color.trim().to_ascii_lowercase();
+    if color.is_empty() {
+        return Err(ColorError::Empty);
+    }
+    if color.starts_with('#') {
+        return from_hex_string(&color);
+    }
+    if color == "transparent" {
+        return Ok(RGBA {
+            r: 0,
+            g: 0,
+            b: 0,
+            a: 0.0,
+        });
+    }
+    if let Some(open) = color.find('(') {
+        return parse_function(&color[..open], &color[open..])
+            .ok_or_else(|| ColorError::InvalidFunction(color.clone()));
+    }
+
+    match NAMED_COLORS.binary_search_by_key(&color.as_str(), |(name, _)| name) {
+        Ok(idx) => from_hex_string(NAMED_COLORS[idx].1),
+        Err(_) => Err(ColorError::Unknown(color)),
+    }
+}
+
+/// Parses the arguments of a CSS color function, separated by commas or by
+/// whitespace with an optional `/` before the alpha
+fn parse_function(name: &str, arguments: &str) -> Option<RGBA> {
+    let arguments = arguments.strip_prefix('(')?.strip_suffix(')')?;
+    let arguments: Vec<&str> = arguments
+        .split(|c: char| c == ',' || c == '/' || c.is_whitespace())
+        .filter(|argument| !argument.is_empty())
+        .collect();
+
+    if !(3..=4).contains(&arguments.len()) {
+        return None;
+    }
+    let alpha = match arguments.get(3) {
+        Some(alpha) => parse_fraction(alpha, 1.0)?,
+        None => 1.0,
+    };
+
+    let color = match name.trim() {
+        "rgb" | "rgba" => {
+            let channel =
+                |argument: &str| parse_fraction(argument, 255.0).map(|c| (c * 255.0).round() as u8);
+            RGBA {
+                r: channel(arguments[0])?,
+                g: channel(arguments[1])?,
+                b: channel(arguments[2])?,
+                a: 1.0,
+            }
+        }
+        "hsl" | "hsla" => {
+            let hue = arguments[0].strip_suffix("deg").unwrap_or(arguments[0]);
+            from_hsl(HSL {
+                h: hue.parse().ok()?,
+                s: parse_fraction(arguments[1], 100.0)?,
+                l: parse_fraction(arguments[2], 100.0)?,
+            })
+        }
+        _ => return None,
+    };
+
+    Some(RGBA {
+        a: alpha as f32,
+        ..color
+    })
+}
+
+/// Parses a percentage, or a number out of `max`, as a fraction from 0 to 1
+fn parse_fraction(value: &str, max: f64) -> Option<f64> {
+    let fraction = match value.strip_suffix('%') {
+        Some(percentage) => percentage.parse::<f64>().ok()? / 100.0,
+        None => value.parse::<f64>().ok()? / max,
+    };
+    if fraction.is_finite() {
+        Some(fraction.clamp(0.0, 1.0))
+    } else {
+        None
+    }
+}
+
+pub fn from_hex_string(hex: &str) -> Result<RGBA, ColorError> {
+    let invalid = || ColorError::InvalidHex(hex.to_string());
+    let digits = hex.strip_prefix('#').ok_or_else(invalid)?;
+    if !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(invalid());
+    }
+
+    let channel = |idx: usize, width: usize| {
+        let digits = &digits[idx * width..(idx + 1) * width];
+        let value = hex_to_u8(digits);
+        if width == 1 {
+            value * 17
+        } else {
+            value
+        }
+    };
+
+    match digits.len() {
+        3 | 4 => Ok(RGBA {
+            r: channel(0, 1),
+            g: channel(1, 1),
+            b: channel(2, 1),
+            a: if digits.len() == 4 {
+                channel(3, 1) as f32 / 255.0
+            } else {
+                1.0
+            },
         }),
-        9 => Ok(RGBA {
-            r: hex_to_u8(&hex[1..3]),
-            g: hex_to_u8(&hex[3..5]),
-            b: hex_to_u8(&hex[5..7]),
-            a: (hex_to_u8(&hex[7..9]) as f32) / 255.0,
+        6 | 8 => Ok(RGBA {
+            r: channel(0, 2),
+            g: channel(1, 2),
+            b: channel(2, 2),
+            a: if digits.len() == 8 {
+                channel(3, 2) as f32 / 255.0
+            } else {
+                1.0
+            },
         }),
-        _ => Err(format!("Unsupported or invalid hex string: {}", hex)),
+        _ => Err(invalid()),
     }
 }
 
+/// Decodes one or two hex digits, which must have been validated
 fn hex_to_u8(hex: &str) -> u8 {
-    u8::from_str_radix(hex, 16).unwrap()
+    u8::from_str_radix(hex, 16).unwrap_or_default()
 }
 
 pub fn to_rgb_hex_string(rgb: RGBA) -> String {
     format!("#{:02x}{:02x}{:02x}", rgb.r, rgb.g, rgb.b,)
 }
 
+/// Like `to_rgb_hex_string`, but keeping the alpha channel when the color
+/// isn't opaque
+pub fn to_rgba_hex_string(rgba: RGBA) -> String {
+    if rgba.a >= 1.0 {
+        return to_rgb_hex_string(rgba);
+    }
+    format!(
+        "#{:02x}{:02x}{:02x}{:02x}",
+        rgba.r,
+        rgba.g,
+        rgba.b,
+        (rgba.a.clamp(0.0, 1.0) * 255.0).round() as u8
+    )
+}
+
 pub fn is_rgba(hex: &str) -> bool {
     hex.len() == 9
 }
@@ -56,7 +205,473 @@ pub fn scale(color: &mut RGBA, scale: f32) {
     color.a = 1.0
 }
 
-#[derive(Debug, Copy, Clone)]
+/// Converts a gamma encoded sRGB channel to linear light
+fn to_linear(channel: u8) -> f64 {
+    let channel = channel as f64 / 255.0;
+    if channel <= 0.04045 {
+        channel / 12.92
+    } else {
+        ((channel + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn from_linear(channel: f64) -> u8 {
+    let channel = if channel <= 0.0031308 {
+        channel * 12.92
+    } else {
+        1.055 * channel.powf(1.0 / 2.4) - 0.055
+    };
+    (channel.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+/// Relative luminance, as defined by WCAG 2
+pub fn relative_luminance(color: RGBA) -> f64 {
+    0.2126 * to_linear(color.r) + 0.7152 * to_linear(color.g) + 0.0722 * to_linear(color.b)
+}
+
+/// WCAG 2 contrast ratio between two colors, from 1 to 21
+pub fn contrast_ratio(first: RGBA, second: RGBA) -> f64 {
+    let (first, second) = (relative_luminance(first), relative_luminance(second));
+    let (lighter, darker) = if first > second {
+        (first, second)
+    } else {
+        (second, first)
+    };
+    (lighter + 0.05) / (darker + 0.05)
+}
+
+/// APCA (0.0.98G) lightness contrast of `text` over `background`, from
+/// about -108 to 106. Negative values mean light text on a dark background.
+pub fn apca_contrast(text: RGBA, background: RGBA) -> f64 {
+    let screen_luminance = |color: RGBA| {
+        let channel = |c: u8| (c as f64 / 255.0).powf(2.4);
+        let y = 0.2126729 * channel(color.r)
+            + 0.7151522 * channel(color.g)
+            + 0.0721750 * channel(color.b);
+        // Soft clamp near black
+        if y < 0.022 {
+            y + (0.022 - y).powf(1.414)
+        } else {
+            y
+        }
+    };
+    let (text, background) = (screen_luminance(text), screen_luminance(background));
+
+    if (background - text).abs() < 0.0005 {
+        return 0.0;
+    }
+
+    let contrast = if background > text {
+        let sapc = (background.powf(0.56) - text.powf(0.57)) * 1.14;
+        if sapc < 0.1 {
+            0.0
+        } else {
+            sapc - 0.027
+        }
+    } else {
+        let sapc = (background.powf(0.65) - text.powf(0.62)) * 1.14;
+        if sapc > -0.1 {
+            0.0
+        } else {
+            sapc + 0.027
+        }
+    };
+
+    contrast * 100.0
+}
+
+/// A color in the HSL space: hue in degrees, saturation and lightness from 0 to 1
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct HSL {
+    pub h: f64,
+    pub s: f64,
+    pub l: f64,
+}
+
+/// A color in the HSV space: hue in degrees, saturation and value from 0 to 1
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct HSV {
+    pub h: f64,
+    pub s: f64,
+    pub v: f64,
+}
+
+/// A color in the CIELAB space, relative to the D65 white point
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Lab {
+    pub l: f64,
+    pub a: f64,
+    pub b: f64,
+}
+
+/// A color in the OKLab space
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct OKLab {
+    pub l: f64,
+    pub a: f64,
+    pub b: f64,
+}
+
+/// A color in the OKLCH space: perceptual lightness (0 to 1), chroma and hue
+/// in degrees
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct OKLCH {
+    pub l: f64,
+    pub c: f64,
+    pub h: f64,
+}
+
+/// Returns the hue (in degrees), the largest and the smallest channel
+fn hue_and_extremes(color: RGBA) -> (f64, f64, f64) {
+    let (r, g, b) = (
+        color.r as f64 / 255.0,
+        color.g as f64 / 255.0,
+        color.b as f64 / 255.0,
+    );
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let delta = max - min;
+
+    let hue = if delta == 0.0 {
+        0.0
+    } else if max == r {
+        60.0 * ((g - b) / delta).rem_euclid(6.0)
+    } else if max == g {
+        60.0 * ((b - r) / delta + 2.0)
+    } else {
+        60.0 * ((r - g) / delta + 4.0)
+    };
+
+    (hue, max, min)
+}
+
+/// Builds a color out of a hue, its chroma and the amount added to every channel
+fn from_hue_and_chroma(hue: f64, chroma: f64, offset: f64, alpha: f32) -> RGBA {
+    let hue = hue.rem_euclid(360.0) / 60.0;
+    let x = chroma * (1.0 - (hue.rem_euclid(2.0) - 1.0).abs());
+    let (r, g, b) = match hue as u8 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let channel = |c: f64| ((c + offset).clamp(0.0, 1.0) * 255.0).round() as u8;
+
+    RGBA {
+        r: channel(r),
+        g: channel(g),
+        b: channel(b),
+        a: alpha,
+    }
+}
+
+pub fn to_hsl(color: RGBA) -> HSL {
+    let (h, max, min) = hue_and_extremes(color);
+    let l = (max + min) / 2.0;
+    let s = if max == min {
+        0.0
+    } else {
+        (max - min) / (1.0 - (2.0 * l - 1.0).abs())
+    };
+    HSL { h, s, l }
+}
+
+pub fn from_hsl(color: HSL) -> RGBA {
+    let chroma = (1.0 - (2.0 * color.l - 1.0).abs()) * color.s;
+    from_hue_and_chroma(color.h, chroma, color.l - chroma / 2.0, 1.0)
+}
+
+pub fn to_hsv(color: RGBA) -> HSV {
+    let (h, max, min) = hue_and_extremes(color);
+    let s = if max == 0.0 { 0.0 } else { (max - min) / max };
+    HSV { h, s, v: max }
+}
+
+pub fn from_hsv(color: HSV) -> RGBA {
+    let chroma = color.v * color.s;
+    from_hue_and_chroma(color.h, chroma, color.v - chroma, 1.0)
+}
+
+/// D65 reference white, in CIE XYZ
+const WHITE: (f64, f64, f64) = (0.95047, 1.0, 1.08883);
+
+pub fn to_lab(color: RGBA) -> Lab {
+    let (r, g, b) = (to_linear(color.r), to_linear(color.g), to_linear(color.b));
+    let x = 0.4124564 * r + 0.3575761 * g + 0.1804375 * b;
+    let y = 0.2126729 * r + 0.7151522 * g + 0.0721750 * b;
+    let z = 0.0193339 * r + 0.1191920 * g + 0.9503041 * b;
+
+    let f = |t: f64| {
+        if t > (6.0f64 / 29.0).powi(3) {
+            t.cbrt()
+        } else {
+            t / (3.0 * (6.0f64 / 29.0).powi(2)) + 4.0 / 29.0
+        }
+    };
+    let (fx, fy, fz) = (f(x / WHITE.0), f(y / WHITE.1), f(z / WHITE.2));
+
+    Lab {
+        l: 116.0 * fy - 16.0,
+        a: 500.0 * (fx - fy),
+        b: 200.0 * (fy - fz),
+    }
+}
+
+pub fn from_lab(color: Lab) -> RGBA {
+    let fy = (color.l + 16.0) / 116.0;
+    let fx = fy + color.a / 500.0;
+    let fz = fy - color.b / 200.0;
+
+    let f_inv = |t: f64| {
+        if t > 6.0 / 29.0 {
+            t.powi(3)
+        } else {
+            3.0 * (6.0f64 / 29.0).powi(2) * (t - 4.0 / 29.0)
+        }
+    };
+    let (x, y, z) = (
+        f_inv(fx) * WHITE.0,
+        f_inv(fy) * WHITE.1,
+        f_inv(fz) * WHITE.2,
+    );
+
+    RGBA {
+        r: from_linear(3.2404542 * x - 1.5371385 * y - 0.4985314 * z),
+        g: from_linear(-0.9692660 * x + 1.8760108 * y + 0.0415560 * z),
+        b: from_linear(0.0556434 * x - 0.2040259 * y + 1.0572252 * z),
+        a: 1.0,
+    }
+}
+
+pub fn to_oklab(color: RGBA) -> OKLab {
+    let (r, g, b) = (to_linear(color.r), to_linear(color.g), to_linear(color.b));
+
+    let l = (0.4122214708 * r + 0.5363325363 * g + 0.0514459929 * b).cbrt();
+    let m = (0.2119034982 * r + 0.6806995451 * g + 0.1073969566 * b).cbrt();
+    let s = (0.0883024619 * r + 0.2817188376 * g + 0.6299787005 * b).cbrt();
+
+    OKLab {
+        l: 0.2104542553 * l + 0.7936177850 * m - 0.0040720468 * s,
+        a: 1.9779984951 * l - 2.4285922050 * m + 0.4505937099 * s,
+        b: 0.0259040371 * l + 0.7827717662 * m - 0.8086757660 * s,
+    }
+}
+
+/// Converts an OKLab color to linear sRGB channels, which may be out of gamut
+fn oklab_to_linear(color: OKLab) -> (f64, f64, f64) {
+    let l = (color.l + 0.3963377774 * color.a + 0.2158037573 * color.b).powi(3);
+    let m = (color.l - 0.1055613458 * color.a - 0.0638541728 * color.b).powi(3);
+    let s = (color.l - 0.0894841775 * color.a - 1.2914855480 * color.b).powi(3);
+
+    (
+        4.0767416621 * l - 3.3077115913 * m + 0.2309699292 * s,
+        -1.2684380046 * l + 2.6097574011 * m - 0.3413193965 * s,
+        -0.0041960863 * l - 0.7034186147 * m + 1.7076147010 * s,
+    )
+}
+
+/// Converts an OKLab color back to sRGB, clipping it if it is out of gamut
+pub fn from_oklab(color: OKLab) -> RGBA {
+    let (r, g, b) = oklab_to_linear(color);
+    RGBA {
+        r: from_linear(r),
+        g: from_linear(g),
+        b: from_linear(b),
+        a: 1.0,
+    }
+}
+
+/// Whether a color reads as light, by its perceptual (OKLCH) lightness
+pub fn is_light(color: RGBA) -> bool {
+    to_oklch(color).l > 0.5
+}
+
+pub fn to_oklch(color: RGBA) -> OKLCH {
+    let OKLab { l, a, b } = to_oklab(color);
+    OKLCH {
+        l,
+        c: (a * a + b * b).sqrt(),
+        h: b.atan2(a).to_degrees().rem_euclid(360.0),
+    }
+}
+
+fn oklch_to_oklab(color: OKLCH) -> OKLab {
+    OKLab {
+        l: color.l,
+        a: color.c * color.h.to_radians().cos(),
+        b: color.c * color.h.to_radians().sin(),
+    }
+}
+
+/// Converts an OKLCH color back to sRGB. Out of gamut colors have their
+/// chroma reduced until they fit, preserving their lightness and hue.
+pub fn from_oklch(color: OKLCH) -> RGBA {
+    let in_gamut = |color: OKLCH| {
+        let (r, g, b) = oklab_to_linear(oklch_to_oklab(color));
+        [r, g, b].iter().all(|c| (-0.0001..=1.0001).contains(c))
+    };
+
+    let mut color = OKLCH {
+        l: color.l.clamp(0.0, 1.0),
+        ..color
+    };
+    if !in_gamut(color) {
+        let (mut low, mut high) = (0.0, color.c);
+        for _ in 0..20 {
+            let mid = (low + high) / 2.0;
+            if in_gamut(OKLCH { c: mid, ..color }) {
+                low = mid;
+            } else {
+                high = mid;
+            }
+        }
+        color.c = low;
+    }
+
+    from_oklab(oklch_to_oklab(color))
+}
+
+/// Euclidean distance in CIELAB (CIE76)
+pub fn delta_e76(first: RGBA, second: RGBA) -> f64 {
+    let (first, second) = (to_lab(first), to_lab(second));
+    ((first.l - second.l).powi(2) + (first.a - second.a).powi(2) + (first.b - second.b).powi(2))
+        .sqrt()
+}
+
+/// CIEDE2000 color difference, where about 1 is a just noticeable difference
+pub fn delta_e2000(first: RGBA, second: RGBA) -> f64 {
+    lab_delta_e2000(to_lab(first), to_lab(second))
+}
+
+fn lab_delta_e2000(first: Lab, second: Lab) -> f64 {
+    let c_bar = ((first.a.powi(2) + first.b.powi(2)).sqrt()
+        + (second.a.powi(2) + second.b.powi(2)).sqrt())
+        / 2.0;
+    let g = 0.5 * (1.0 - (c_bar.powi(7) / (c_bar.powi(7) + 25f64.powi(7))).sqrt());
+
+    let a1 = (1.0 + g) * first.a;
+    let a2 = (1.0 + g) * second.a;
+    let c1 = (a1.powi(2) + first.b.powi(2)).sqrt();
+    let c2 = (a2.powi(2) + second.b.powi(2)).sqrt();
+    let hue = |b: f64, a: f64| {
+        if a == 0.0 && b == 0.0 {
+            0.0
+        } else {
+            b.atan2(a).to_degrees().rem_euclid(360.0)
+        }
+    };
+    let h1 = hue(first.b, a1);
+    let h2 = hue(second.b, a2);
+
+    let delta_l = second.l - first.l;
+    let delta_c = c2 - c1;
+    let delta_h = if c1 * c2 == 0.0 {
+        0.0
+    } else if (h2 - h1).abs() <= 180.0 {
+        h2 - h1
+    } else if h2 - h1 > 180.0 {
+        h2 - h1 - 360.0
+    } else {
+        h2 - h1 + 360.0
+    };
+    let delta_big_h = 2.0 * (c1 * c2).sqrt() * (delta_h / 2.0).to_radians().sin();
+
+    let l_bar = (first.l + second.l) / 2.0;
+    let c_bar = (c1 + c2) / 2.0;
+    let h_bar = if c1 * c2 == 0.0 {
+        h1 + h2
+    } else if (h1 - h2).abs() <= 180.0 {
+        (h1 + h2) / 2.0
+    } else if h1 + h2 < 360.0 {
+        (h1 + h2 + 360.0) / 2.0
+    } else {
+        (h1 + h2 - 360.0) / 2.0
+    };
+
+    let t = 1.0 - 0.17 * (h_bar - 30.0).to_radians().cos()
+        + 0.24 * (2.0 * h_bar).to_radians().cos()
+        + 0.32 * (3.0 * h_bar + 6.0).to_radians().cos()
+        - 0.20 * (4.0 * h_bar - 63.0).to_radians().cos();
+    let delta_theta = 30.0 * (-((h_bar - 275.0) / 25.0).powi(2)).exp();
+    let r_c = 2.0 * (c_bar.powi(7) / (c_bar.powi(7) + 25f64.powi(7))).sqrt();
+    let s_l = 1.0 + (0.015 * (l_bar - 50.0).powi(2)) / (20.0 + (l_bar - 50.0).powi(2)).sqrt();
+    let s_c = 1.0 + 0.045 * c_bar;
+    let s_h = 1.0 + 0.015 * c_bar * t;
+    let r_t = -(2.0 * delta_theta).to_radians().sin() * r_c;
+
+    ((delta_l / s_l).powi(2)
+        + (delta_c / s_c).powi(2)
+        + (delta_big_h / s_h).powi(2)
+        + r_t * (delta_c / s_c) * (delta_big_h / s_h))
+        .sqrt()
+}
+
+/// Euclidean distance in OKLab, where about 0.02 is a just noticeable difference
+pub fn delta_e_ok(first: RGBA, second: RGBA) -> f64 {
+    let (first, second) = (to_oklab(first), to_oklab(second));
+    ((first.l - second.l).powi(2) + (first.a - second.a).powi(2) + (first.b - second.b).powi(2))
+        .sqrt()
+}
+
+/// Applies `f` to the OKLCH representation of `color`, keeping its alpha
+fn map_oklch<F: FnOnce(OKLCH) -> OKLCH>(color: RGBA, f: F) -> RGBA {
+    RGBA {
+        a: color.a,
+        ..from_oklch(f(to_oklch(color)))
+    }
+}
+
+/// Increases the OKLCH lightness of `color` by `amount` (0 to 1)
+pub fn lighten(color: RGBA, amount: f64) -> RGBA {
+    map_oklch(color, |c| OKLCH {
+        l: c.l + amount,
+        ..c
+    })
+}
+
+/// Decreases the OKLCH lightness of `color` by `amount` (0 to 1)
+pub fn darken(color: RGBA, amount: f64) -> RGBA {
+    lighten(color, -amount)
+}
+
+/// Multiplies the OKLCH chroma of `color` by `1 + amount`
+pub fn saturate(color: RGBA, amount: f64) -> RGBA {
+    map_oklch(color, |c| OKLCH {
+        c: (c.c * (1.0 + amount)).max(0.0),
+        ..c
+    })
+}
+
+/// Multiplies the OKLCH chroma of `color` by `1 - amount`
+pub fn desaturate(color: RGBA, amount: f64) -> RGBA {
+    saturate(color, -amount)
+}
+
+/// Interpolates from `first` to `second` in OKLCH, taking the shortest way
+/// around the hue circle. `amount` goes from 0 (`first`) to 1 (`second`).
+pub fn mix(first: RGBA, second: RGBA, amount: f64) -> RGBA {
+    let (from, to) = (to_oklch(first), to_oklch(second));
+    // Grays have no meaningful hue, so they take the one of the other color
+    let (from_h, to_h) = match (from.c < 1e-4, to.c < 1e-4) {
+        (true, false) => (to.h, to.h),
+        (false, true) => (from.h, from.h),
+        _ => (from.h, to.h),
+    };
+    let hue_delta = ((to_h - from_h + 180.0).rem_euclid(360.0)) - 180.0;
+
+    RGBA {
+        a: first.a + (second.a - first.a) * amount as f32,
+        ..from_oklch(OKLCH {
+            l: from.l + (to.l - from.l) * amount,
+            c: from.c + (to.c - from.c) * amount,
+            h: (from_h + hue_delta * amount).rem_euclid(360.0),
+        })
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub struct RGBA {
     pub r: u8,
     pub g: u8,
@@ -122,6 +737,34 @@ mod tests {
         }
     }
 
+    #[test]
+    fn can_parse_css_colors() {
+        let rgba = |color: &str| {
+            let color = parse(color).unwrap();
+            (color.r, color.g, color.b, color.a)
+        };
+
+        assert_eq!(rgba(" #0F0FFF "), (15, 15, 255, 1.0));
+        assert_eq!(rgba("#f0f8"), (255, 0, 255, 0.53333336));
+        assert_eq!(rgba("rgb(255, 128, 0)"), (255, 128, 0, 1.0));
+        assert_eq!(rgba("RGBA(100%, 0%, 0%, 0.5)"), (255, 0, 0, 0.5));
+        assert_eq!(rgba("rgb(0 0 255 / 25%)"), (0, 0, 255, 0.25));
+        assert_eq!(rgba("hsl(120deg, 100%, 25%)"), (0, 128, 0, 1.0));
+        assert_eq!(rgba("hsla(0, 100%, 50%, .4)"), (255, 0, 0, 0.4));
+        assert_eq!(rgba("RebeccaPurple"), (102, 51, 153, 1.0));
+        assert_eq!(rgba("transparent").3, 0.0);
+
+        assert_eq!(parse(""), Err(ColorError::Empty));
+        assert!(matches!(parse("#zz0000"), Err(ColorError::InvalidHex(_))));
+        assert!(matches!(parse("#12345"), Err(ColorError::InvalidHex(_))));
+        assert!(matches!(parse("#ffé"), Err(ColorError::InvalidHex(_))));
+        assert!(matches!(
+            parse("rgb(1, 2)"),
+            Err(ColorError::InvalidFunction(_))
+        ));
+        assert!(matches!(parse("notacolor"), Err(ColorError::Unknown(_))));
+    }
+
     #[test]
     fn can_blend_colors() {
         let background = RGBA {
@@ -149,4 +792,124 @@ mod tests {
             (target.r, target.g, target.b)
         )
     }
+
+    #[test]
+    fn can_compute_contrast() {
+        let black = RGBA {
+            r: 0,
+            g: 0,
+            b: 0,
+            a: 1.0,
+        };
+        let white = RGBA {
+            r: 255,
+            g: 255,
+            b: 255,
+            a: 1.0,
+        };
+        let gray = RGBA {
+            r: 136,
+            g: 136,
+            b: 136,
+            a: 1.0,
+        };
+
+        assert!((contrast_ratio(black, white) - 21.0).abs() < 0.01);
+        assert!((apca_contrast(black, white) - 106.04).abs() < 0.1);
+        assert!((apca_contrast(white, black) + 107.88).abs() < 0.1);
+        assert!((apca_contrast(gray, white) - 63.06).abs() < 0.1);
+    }
+
+    #[test]
+    fn can_convert_oklch() {
+        let color = RGBA {
+            r: 203,
+            g: 166,
+            b: 247,
+            a: 1.0,
+        };
+
+        let result = from_oklch(to_oklch(color));
+        assert_eq!((result.r, result.g, result.b), (color.r, color.g, color.b))
+    }
+
+    #[test]
+    fn can_convert_hsl_and_hsv() {
+        let color = RGBA {
+            r: 255,
+            g: 128,
+            b: 0,
+            a: 1.0,
+        };
+
+        let hsl = to_hsl(color);
+        assert!((hsl.h - 30.1).abs() < 0.1 && (hsl.s - 1.0).abs() < 0.01);
+        let hsv = to_hsv(color);
+        assert!((hsv.v - 1.0).abs() < 0.01 && (hsv.s - 1.0).abs() < 0.01);
+
+        let from_hsl = from_hsl(hsl);
+        let from_hsv = from_hsv(hsv);
+        assert_eq!((from_hsl.r, from_hsl.g, from_hsl.b), (255, 128, 0));
+        assert_eq!((from_hsv.r, from_hsv.g, from_hsv.b), (255, 128, 0))
+    }
+
+    #[test]
+    fn can_convert_lab() {
+        let red = RGBA {
+            r: 255,
+            g: 0,
+            b: 0,
+            a: 1.0,
+        };
+
+        let lab = to_lab(red);
+        assert!((lab.l - 53.24).abs() < 0.05);
+        assert!((lab.a - 80.09).abs() < 0.05);
+        assert!((lab.b - 67.20).abs() < 0.05);
+
+        let result = from_lab(lab);
+        assert_eq!((result.r, result.g, result.b), (255, 0, 0))
+    }
+
+    #[test]
+    fn can_compute_ciede2000() {
+        // First pair of Sharma, Wu and Dalal's test data
+        let first = Lab {
+            l: 50.0,
+            a: 2.6772,
+            b: -79.7751,
+        };
+        let second = Lab {
+            l: 50.0,
+            a: 0.0,
+            b: -82.7485,
+        };
+
+        assert!((lab_delta_e2000(first, second) - 2.0425).abs() < 0.0001)
+    }
+
+    #[test]
+    fn can_manipulate_in_oklch() {
+        let color = RGBA {
+            r: 100,
+            g: 60,
+            b: 200,
+            a: 0.5,
+        };
+        let white = RGBA {
+            r: 255,
+            g: 255,
+            b: 255,
+            a: 1.0,
+        };
+
+        let lighter = lighten(color, 0.1);
+        assert!((to_oklch(lighter).l - to_oklch(color).l - 0.1).abs() < 0.01);
+        assert!((to_oklch(lighter).h - to_oklch(color).h).abs() < 2.0);
+        assert_eq!(lighter.a, 0.5);
+
+        let mixed = mix(color, white, 1.0);
+        assert_eq!((mixed.r, mixed.g, mixed.b), (255, 255, 255));
+        assert!(desaturate(color, 1.0).r.abs_diff(desaturate(color, 1.0).b) <= 1)
+    }
 }
@@ -0,0 +1,259 @@
+use indoc::indoc;
+
+use crate::{
+    generator::{indent, ConfigGenerator},
+    highlights::VimHighlight,
+    ir::ResolvedTheme,
+    lua::{escape, quote, EXPORTS},
+};
+
+const HEADER: &str = indoc! {"
+    -- Colorscheme generated by https://github.com/arcticlimer/djanho
+    -- Compiled to direct nvim_set_hl calls. Loading it with a namespace, as in
+    -- loadfile(path)(ns), sets the highlights of that namespace instead.
+    local ns = ... or {default_ns}
+    local set_hl = vim.api.nvim_set_hl
+
+    if ns == 0 then
+        vim.cmd[[highlight clear]]
+        vim.g.colors_name = '{name}'
+    end
+
+    -- Local tweaks that survive regenerating the colorscheme go in
+    -- lua/{name}/overrides.lua, which returns the groups to change, or a
+    -- function that fills them in given the palette:
+    --   return function(hl, palette) hl.Comment = { fg = palette.Color3, style = 'NONE' } end
+    local apply_overrides = function(palette)
+        package.loaded['{name}.overrides'] = nil
+        local ok, overrides = pcall(require, '{name}.overrides')
+        if not ok then
+            if not overrides:find(\"module '{name}.overrides' not found\", 1, true) then
+                error(overrides)
+            end
+            return
+        end
+
+        if type(overrides) == 'function' then
+            local hl = {}
+            overrides = overrides(hl, palette) or hl
+        end
+        for group, attrs in pairs(overrides) do
+            local spec = { link = attrs.link }
+            if not attrs.link then
+                spec = vim.api.nvim_get_hl(ns, { name = group, link = false })
+                for _, key in ipairs({ 'fg', 'bg', 'sp' }) do
+                    if attrs[key] then
+                        spec[key] = palette[attrs[key]] or attrs[key]
+                    end
+                end
+                if attrs.style then
+                    for _, name in ipairs({ 'bold', 'italic', 'underline', 'undercurl', 'strikethrough', 'reverse' }) do
+                        spec[name] = nil
+                    end
+                    for name in attrs.style:gmatch('[^,]+') do
+                        spec[name] = name ~= 'NONE' or nil
+                    end
+                end
+            end
+            set_hl(ns, group, spec)
+        end
+    end\n"
+};
+
+/// Renders Lua colorschemes as one `nvim_set_hl` call per group, with the
+/// colors inlined, which Neovim loads faster than `:highlight` commands
+#[derive(Debug)]
+pub struct CompiledGenerator {
+    /// Name of the colorscheme, which the user overrides module is named after
+    pub name: String,
+    /// Whether the highlights are set in the namespace of the theme by
+    /// default, instead of globally
+    pub namespace: bool,
+}
+
+impl CompiledGenerator {
+    pub fn new<S: Into<String>>(name: S) -> CompiledGenerator {
+        CompiledGenerator {
+            name: name.into(),
+            namespace: false,
+        }
+    }
+
+    pub fn with_namespace(mut self, namespace: bool) -> CompiledGenerator {
+        self.namespace = namespace;
+        self
+    }
+
+    fn header(&self) -> String {
+        let default_ns = if self.namespace {
+            format!("vim.api.nvim_create_namespace({})", quote(&self.name))
+        } else {
+            "0".to_string()
+        };
+        HEADER
+            .replace("{name}", &escape(&self.name))
+            .replace("{default_ns}", &default_ns)
+    }
+
+    fn footer(&self) -> &'static str {
+        if self.namespace {
+            EXPORTS
+        } else {
+            ""
+        }
+    }
+}
+
+impl ConfigGenerator for CompiledGenerator {
+    fn render(&self, theme: &ResolvedTheme) -> String {
+        self.header() + &body(theme) + self.footer()
+    }
+
+    fn render_pair(&self, dark: &ResolvedTheme, light: &ResolvedTheme) -> String {
+        let mut buffer = self.header();
+        buffer += "\nif vim.o.background == 'light' then\n";
+        buffer += &indent(body(light).trim_start(), 4);
+        buffer += "else\n";
+        buffer += &indent(body(dark).trim_start(), 4);
+        buffer += "end\n";
+        buffer += self.footer();
+        buffer
+    }
+}
+
+fn body(theme: &ResolvedTheme) -> String {
+    let mut buffer = String::new();
+
+    if let Some(note) = theme.metadata.synthesized_note() {
+        buffer += &format!("\n-- {}\n", note);
+    }
+
+    buffer += "\n";
+    for highlight in merge(&theme.highlights) {
+        let spec = spec(theme, &highlight);
+        if !spec.is_empty() {
+            buffer += &format!("set_hl(ns, {}, {{ {} }})\n", quote(&highlight.group), spec);
+        }
+    }
+
+    buffer += "\n";
+    for link in &theme.links {
+        buffer += &format!(
+            "set_hl(ns, {}, {{ link = {} }})\n",
+            quote(&link.group),
+            quote(&link.target)
+        );
+    }
+
+    let palette: Vec<String> = theme
+        .palette
+        .iter()
+        .map(|color| format!("{} = {}", color.name, quote(&color.color)))
+        .collect();
+    buffer += &format!("\napply_overrides({{ {} }})\n", palette.join(", "));
+
+    buffer
+}
+
+/// Merges the definitions of each group, as `nvim_set_hl` replaces the whole
+/// group while `:highlight` only sets the given attributes
+fn merge(highlights: &[VimHighlight]) -> Vec<VimHighlight> {
+    let mut merged: Vec<VimHighlight> = Vec::new();
+    for highlight in highlights {
+        match merged.iter_mut().find(|h| h.group == highlight.group) {
+            Some(group) => {
+                if highlight.background.is_some() {
+                    group.background = highlight.background.clone();
+                }
+                if highlight.foreground.is_some() {
+                    group.foreground = highlight.foreground.clone();
+                }
+                if highlight.special.is_some() {
+                    group.special = highlight.special.clone();
+                }
+                if highlight.text_style.is_some() {
+                    group.text_style = highlight.text_style.clone();
+                }
+            }
+            None => merged.push(highlight.clone()),
+        }
+    }
+    merged
+}
+
+/// The attributes of a highlight, as the fields of a `nvim_set_hl` table
+fn spec(theme: &ResolvedTheme, highlight: &VimHighlight) -> String {
+    let mut fields: Vec<String> = Vec::new();
+    let colors = [
+        ("fg", &highlight.foreground),
+        ("bg", &highlight.background),
+        ("sp", &highlight.special),
+    ];
+    for (key, name) in colors.iter() {
+        let color = name.as_ref().and_then(|name| {
+            theme
+                .palette
+                .iter()
+                .find(|color| &color.name == name)
+                .map(|color| &color.color)
+        });
+        if let Some(color) = color {
+            fields.push(format!("{} = {}", key, quote(color)));
+        }
+    }
+    for style in highlight
+        .text_style
+        .iter()
+        .flat_map(|style| style.split(','))
+    {
+        if style != "NONE" {
+            fields.push(format!("{} = true", style));
+        }
+    }
+    fields.join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::{lua::LuaGenerator, theme::Theme};
+
+    const FIXTURE: &str = r##"{
+        "colors": {
+            "editor.background": "#1e1e2e",
+            "editor.foreground": "#cdd6f4",
+            "editorError.foreground": "#f38ba8"
+        },
+        "tokenColors": [
+            { "scope": "comment", "settings": { "foreground": "#6c7086", "fontStyle": "italic" } },
+            { "scope": "keyword", "settings": { "foreground": "#cba6f7", "fontStyle": "bold" } },
+            { "scope": "string", "settings": { "foreground": "#a6e3a1" } },
+            { "scope": "source.rust string", "settings": { "foreground": "#94e2d5" } }
+        ]
+    }"##;
+
+    #[test]
+    fn can_compile_one_call_per_group() {
+        let theme: Theme = FIXTURE.parse().unwrap();
+        let resolved = theme.resolve();
+
+        let compiled = CompiledGenerator::new("fixture").render(&resolved);
+        let calls = compiled.matches("\nset_hl(ns, ").count();
+        let groups: HashSet<&str> = resolved
+            .highlights
+            .iter()
+            .filter(|highlight| highlight.has_attributes())
+            .map(|highlight| highlight.group.as_str())
+            .chain(resolved.links.iter().map(|link| link.group.as_str()))
+            .collect();
+        assert_eq!(calls, groups.len());
+        assert!(!compiled.contains("nvim_command"));
+        assert!(compiled.contains("set_hl(ns, 'Comment', { fg = '#6c7086', italic = true })"));
+
+        let lua = LuaGenerator::new("fixture").render(&resolved);
+        let commands = lua.matches("\nhighlight(").count() + lua.matches("\nlink(").count();
+        assert!(calls <= commands);
+    }
+}
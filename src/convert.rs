@@ -0,0 +1,335 @@
+use std::path::PathBuf;
+
+use crate::{
+    compiled::CompiledGenerator,
+    error::{Error, Result},
+    generator::ConfigGenerator,
+    highlights::TRANSPARENT_GROUPS,
+    ir::{self, InvalidColor, ResolvedTheme},
+    lua::{self, LuaGenerator},
+    synthesize,
+    theme::Theme,
+    validate,
+    vimscript::VimscriptGenerator,
+};
+
+/// The language of the generated colorscheme
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Target {
+    Vimscript,
+    Lua,
+}
+
+impl Target {
+    pub fn extension(self) -> &'static str {
+        match self {
+            Target::Vimscript => "vim",
+            Target::Lua => "lua",
+        }
+    }
+
+    fn generator(self, options: &Options) -> Box<dyn ConfigGenerator> {
+        match self {
+            Target::Vimscript => Box::new(VimscriptGenerator::new(&options.name)),
+            Target::Lua if options.lua_module => {
+                Box::new(LuaGenerator::module(&options.name).with_namespace(options.namespace))
+            }
+            Target::Lua if options.compiled => {
+                Box::new(CompiledGenerator::new(&options.name).with_namespace(options.namespace))
+            }
+            Target::Lua => {
+                Box::new(LuaGenerator::new(&options.name).with_namespace(options.namespace))
+            }
+        }
+    }
+}
+
+/// Options for `convert_with`, created through `Options::builder`
+#[derive(Debug, Clone)]
+pub struct Options {
+    name: String,
+    invert_variant: bool,
+    transparent: bool,
+    lua_module: bool,
+    compiled: bool,
+    namespace: bool,
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        Options {
+            name: "generated".to_string(),
+            invert_variant: false,
+            transparent: false,
+            lua_module: false,
+            compiled: false,
+            namespace: false,
+        }
+    }
+}
+
+impl Options {
+    pub fn builder() -> OptionsBuilder {
+        OptionsBuilder::default()
+    }
+
+    /// Name of the colorscheme, used as the stem of the generated files
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Whether a light variant is synthesized out of a dark theme, or a dark
+    /// variant out of a light one
+    pub fn invert_variant(&self) -> bool {
+        self.invert_variant
+    }
+
+    /// Whether the background of `Normal`, `SignColumn`, `NormalFloat` and
+    /// the like is cleared, letting the terminal background show through
+    pub fn transparent(&self) -> bool {
+        self.transparent
+    }
+
+    /// Whether Lua colorschemes are generated as a `lua/<name>/init.lua`
+    /// module with `setup` and `load` functions, loaded by `colors/<name>.lua`
+    pub fn lua_module(&self) -> bool {
+        self.lua_module
+    }
+
+    /// Whether Lua colorschemes are compiled to direct `nvim_set_hl` calls,
+    /// which load faster. Modules can't be compiled.
+    pub fn compiled(&self) -> bool {
+        self.compiled
+    }
+
+    /// Whether Lua colorschemes set their highlights in a namespace named
+    /// after them, and export an `apply(win)` function showing it in a
+    /// window, instead of setting them globally
+    pub fn namespace(&self) -> bool {
+        self.namespace
+    }
+
+    /// Checks that the options can be used together, and that the name can
+    /// be used in the paths of the generated files
+    fn check(&self) -> Result<()> {
+        if self.lua_module && self.compiled {
+            return Err(Error::ConflictingOptions(
+                "Lua modules can't be compiled to nvim_set_hl calls",
+            ));
+        }
+        let name = &self.name;
+        if name.is_empty() || name.contains(['/', '\\']) || name.contains("..") {
+            return Err(Error::InvalidName(name.clone()));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct OptionsBuilder {
+    options: Options,
+}
+
+impl OptionsBuilder {
+    pub fn name<S: Into<String>>(mut self, name: S) -> OptionsBuilder {
+        self.options.name = name.into();
+        self
+    }
+
+    pub fn invert_variant(mut self, invert_variant: bool) -> OptionsBuilder {
+        self.options.invert_variant = invert_variant;
+        self
+    }
+
+    pub fn transparent(mut self, transparent: bool) -> OptionsBuilder {
+        self.options.transparent = transparent;
+        self
+    }
+
+    pub fn lua_module(mut self, lua_module: bool) -> OptionsBuilder {
+        self.options.lua_module = lua_module;
+        self
+    }
+
+    pub fn compiled(mut self, compiled: bool) -> OptionsBuilder {
+        self.options.compiled = compiled;
+        self
+    }
+
+    pub fn namespace(mut self, namespace: bool) -> OptionsBuilder {
+        self.options.namespace = namespace;
+        self
+    }
+
+    pub fn build(self) -> Options {
+        self.options
+    }
+}
+
+/// A generated file, with a path relative to the output directory
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutputFile {
+    pub path: PathBuf,
+    pub contents: String,
+}
+
+/// Every file generated by a conversion
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Output {
+    pub files: Vec<OutputFile>,
+    /// Colors of the theme that couldn't be parsed, and were left out
+    pub invalid_colors: Vec<InvalidColor>,
+}
+
+/// Converts `theme` to `target` using the default `Options`
+pub fn convert(theme: &Theme, target: Target) -> Result<Output> {
+    convert_with(theme, target, &Options::default())
+}
+
+pub fn convert_with(theme: &Theme, target: Target, options: &Options) -> Result<Output> {
+    convert_resolved(&resolve_with(theme, options), target, options)
+}
+
+/// Resolves `theme`, synthesizing its other variant and clearing backgrounds
+/// as `options` asks, i.e. into what `convert_with` renders
+pub fn resolve_with(theme: &Theme, options: &Options) -> ResolvedTheme {
+    let mut resolved = theme.resolve();
+    if options.invert_variant {
+        resolved = synthesize::invert_variant(&resolved);
+    }
+    if options.transparent {
+        resolved.clear_backgrounds(TRANSPARENT_GROUPS);
+    }
+    resolved
+}
+
+/// Renders an already resolved theme, e.g. after adjusting it
+pub fn convert_resolved(
+    resolved: &ResolvedTheme,
+    target: Target,
+    options: &Options,
+) -> Result<Output> {
+    options.check()?;
+    let contents = target.generator(options).render(resolved);
+    Ok(Output {
+        files: files(contents, target, options)?,
+        invalid_colors: resolved.invalid_colors.clone(),
+    })
+}
+
+/// Converts the dark and light variants of a theme into a single colorscheme,
+/// which switches between them according to `background`. Fails when
+/// `options` asks for a synthesized variant.
+pub fn convert_pair(
+    dark: &Theme,
+    light: &Theme,
+    target: Target,
+    options: &Options,
+) -> Result<Output> {
+    options.check()?;
+    if options.invert_variant {
+        return Err(Error::ConflictingOptions(
+            "A variant can't be synthesized when both the dark and light ones are given",
+        ));
+    }
+    let (mut dark, mut light) = (dark.resolve(), light.resolve());
+    if options.transparent {
+        dark.clear_backgrounds(TRANSPARENT_GROUPS);
+        light.clear_backgrounds(TRANSPARENT_GROUPS);
+    }
+    ir::share_palette(&mut dark, &mut light);
+
+    let contents = target.generator(options).render_pair(&dark, &light);
+    let mut invalid_colors = dark.invalid_colors;
+    invalid_colors.extend(light.invalid_colors);
+    Ok(Output {
+        files: files(contents, target, options)?,
+        invalid_colors,
+    })
+}
+
+/// Gathers the generated files, checking that they would load
+fn files(contents: String, target: Target, options: &Options) -> Result<Vec<OutputFile>> {
+    let files = if target == Target::Lua && options.lua_module {
+        vec![
+            OutputFile {
+                path: PathBuf::from("colors").join(format!("{}.lua", options.name)),
+                contents: lua::loader(&options.name),
+            },
+            OutputFile {
+                path: PathBuf::from("lua").join(&options.name).join("init.lua"),
+                contents,
+            },
+        ]
+    } else {
+        vec![OutputFile {
+            path: PathBuf::from(format!("{}.{}", options.name, target.extension())),
+            contents,
+        }]
+    };
+
+    for file in &files {
+        validate::validate(file)?;
+    }
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{error::Error, highlights::VimHighlight, ir::PaletteColor};
+
+    #[test]
+    fn can_reject_invalid_options() {
+        let theme: Theme = r##"{ "colors": { "editor.background": "#1e1e2e" } }"##
+            .parse()
+            .unwrap();
+        let convert = |options: OptionsBuilder| convert_with(&theme, Target::Lua, &options.build());
+
+        let conflicting = Options::builder().lua_module(true).compiled(true);
+        assert!(matches!(
+            convert(conflicting),
+            Err(Error::ConflictingOptions(_))
+        ));
+        for name in ["../x", "a/b", "a\\b", ""].iter() {
+            let options = Options::builder().lua_module(true).name(*name);
+            assert!(matches!(convert(options), Err(Error::InvalidName(_))));
+        }
+        assert!(convert(Options::builder().name("my-theme.dark")).is_ok());
+    }
+
+    #[test]
+    fn can_load_palettes_past_the_luajit_locals_limit() {
+        let theme: Theme = "{}".parse().unwrap();
+        let mut resolved = theme.resolve();
+        for idx in 0..300 {
+            let name = format!("Color{}", resolved.palette.len());
+            resolved.palette.push(PaletteColor {
+                name: name.clone(),
+                color: format!("#{:06x}", idx),
+            });
+            resolved.highlights.push(VimHighlight {
+                group: format!("Group{}", idx),
+                background: None,
+                foreground: Some(name),
+                special: None,
+                text_style: None,
+                sources: Vec::new(),
+            });
+        }
+
+        // The output is validated with LuaJIT as it is converted
+        let builders = [
+            Options::builder(),
+            Options::builder().lua_module(true),
+            Options::builder().namespace(true),
+            Options::builder().compiled(true),
+        ];
+        for options in builders.iter() {
+            let options = options.clone().build();
+            if let Err(err) = convert_resolved(&resolved, Target::Lua, &options) {
+                panic!("{:?}: {}", options, err);
+            }
+        }
+    }
+}
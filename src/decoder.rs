@@ -1,21 +1,86 @@
-use std::collections::HashMap;
-use std::fs;
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::{Path, PathBuf};
 
 use json_comments::StripComments;
 use serde::{Deserialize, Serialize};
 
-pub fn parse_file(filepath: String) -> VSCodeTheme {
-    let body = fs::read_to_string(filepath).expect("Could not read the file.");
-    let stripped = StripComments::new(body.as_bytes());
-    let theme: VSCodeTheme = serde_json::from_reader(stripped).unwrap();
-    theme
+use crate::error::{Error, Result};
+
+pub fn parse_file<P: AsRef<Path>>(filepath: P) -> Result<VSCodeTheme> {
+    parse_file_with_includes(filepath.as_ref(), &mut Vec::new())
+}
+
+/// Decodes the theme at `filepath` merged with the themes it `include`s,
+/// pushing every file that was read to `files`.
+pub fn parse_file_with_includes(filepath: &Path, files: &mut Vec<PathBuf>) -> Result<VSCodeTheme> {
+    let filepath = filepath.canonicalize().map_err(Error::io(filepath))?;
+    if files.contains(&filepath) {
+        return Err(Error::IncludeCycle(filepath));
+    }
+    files.push(filepath.clone());
+
+    let file = File::open(&filepath).map_err(Error::io(&filepath))?;
+    let mut theme = parse_reader(BufReader::new(file))?;
+
+    if let Some(include) = theme.include.take() {
+        let include = filepath
+            .parent()
+            .unwrap_or_else(|| Path::new(""))
+            .join(include);
+        let base = parse_file_with_includes(&include, files)?;
+        theme = merge(base, theme);
+    }
+
+    Ok(theme)
+}
+
+/// Decodes a VSCode theme, allowing comments in the JSON
+pub fn parse_reader<R: Read>(reader: R) -> Result<VSCodeTheme> {
+    let stripped = StripComments::new(reader);
+    Ok(serde_json::from_reader(stripped)?)
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct VSCodeTheme {
-    #[serde(rename = "tokenColors")]
+    pub name: Option<String>,
+    #[serde(rename = "type")]
+    pub kind: Option<String>,
+    /// Path of a theme this one extends, relative to this theme
+    pub include: Option<String>,
+    #[serde(rename = "tokenColors", default)]
     pub tokens: Vec<VSCodeHighlight>,
     pub colors: Option<HashMap<String, String>>,
+    /// Styles of LSP semantic tokens, by selector (e.g. `variable.readonly:rust`)
+    #[serde(rename = "semanticTokenColors", default)]
+    pub semantic_tokens: BTreeMap<String, VSCodeSemanticStyle>,
+}
+
+/// Applies `theme` on top of the theme it includes
+fn merge(base: VSCodeTheme, theme: VSCodeTheme) -> VSCodeTheme {
+    let colors = match (base.colors, theme.colors) {
+        (Some(mut base_colors), Some(colors)) => {
+            base_colors.extend(colors);
+            Some(base_colors)
+        }
+        (base_colors, colors) => colors.or(base_colors),
+    };
+
+    let mut tokens = base.tokens;
+    tokens.extend(theme.tokens);
+
+    let mut semantic_tokens = base.semantic_tokens;
+    semantic_tokens.extend(theme.semantic_tokens);
+
+    VSCodeTheme {
+        name: theme.name.or(base.name),
+        kind: theme.kind.or(base.kind),
+        include: None,
+        tokens,
+        colors,
+        semantic_tokens,
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -39,3 +104,65 @@ pub struct VSCodeScopeSettings {
     #[serde(rename = "fontStyle")]
     pub font_style: Option<String>,
 }
+
+/// A `semanticTokenColors` entry, either a foreground color or a style
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum VSCodeSemanticStyle {
+    Color(String),
+    Style(VSCodeSemanticSettings),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct VSCodeSemanticSettings {
+    pub foreground: Option<String>,
+    #[serde(rename = "fontStyle")]
+    pub font_style: Option<String>,
+    pub bold: Option<bool>,
+    pub italic: Option<bool>,
+    pub underline: Option<bool>,
+    pub strikethrough: Option<bool>,
+}
+
+impl VSCodeSemanticStyle {
+    /// The style as `tokenColors` settings, where the `bold`, `italic`,
+    /// `underline` and `strikethrough` flags take precedence over `fontStyle`
+    pub fn settings(&self) -> VSCodeScopeSettings {
+        let style = match self {
+            VSCodeSemanticStyle::Color(color) => {
+                return VSCodeScopeSettings {
+                    foreground: Some(color.clone()),
+                    background: None,
+                    font_style: None,
+                }
+            }
+            VSCodeSemanticStyle::Style(style) => style,
+        };
+
+        let mut font_style: Option<Vec<&str>> = style
+            .font_style
+            .as_deref()
+            .map(|font_style| font_style.split_whitespace().collect());
+        let flags = [
+            ("bold", style.bold),
+            ("italic", style.italic),
+            ("underline", style.underline),
+            ("strikethrough", style.strikethrough),
+        ];
+        for (name, flag) in flags.iter() {
+            if let Some(flag) = flag {
+                let attributes = font_style.get_or_insert_with(Vec::new);
+                attributes.retain(|attribute| attribute != name);
+                if *flag {
+                    attributes.push(name);
+                }
+            }
+        }
+
+        VSCodeScopeSettings {
+            foreground: style.foreground.clone(),
+            background: None,
+            font_style: font_style.map(|attributes| attributes.join(" ")),
+        }
+    }
+}
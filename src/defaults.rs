@@ -0,0 +1,411 @@
+use std::collections::HashMap;
+
+use crate::{
+    colors::{self, HSL, RGBA},
+    decoder::VSCodeTheme,
+};
+
+/// How VSCode computes a color the theme doesn't define
+#[derive(Debug, Copy, Clone)]
+pub enum Derivation {
+    /// A fixed hex color
+    Hex(&'static str),
+    /// The value of another color key
+    Key(&'static str),
+    /// Another color key with its alpha multiplied by a factor
+    Transparent(&'static str, f32),
+    /// Another color key with its HSL lightness reduced by a factor
+    Darken(&'static str, f64),
+    /// Another color key with its HSL lightness increased by a factor
+    Lighten(&'static str, f64),
+}
+
+use Derivation::*;
+
+/// A tuple containing (VSCode color key, dark theme default, light theme default)
+type ColorDefault = (&'static str, Derivation, Derivation);
+
+/// Defaults of the color keys djanho maps, mirroring VSCode's color registry.
+/// Keys are resolved in order, so a derivation can only refer to the ones
+/// above it.
+pub fn defaults() -> Vec<ColorDefault> {
+    vec![
+        // Editor
+        ("editor.background", Hex("#1e1e1e"), Hex("#ffffff")),
+        ("editor.foreground", Hex("#d4d4d4"), Hex("#000000")),
+        ("editor.selectionBackground", Hex("#264f78"), Hex("#add6ff")),
+        (
+            "editor.lineHighlightBackground",
+            Lighten("editor.background", 0.3),
+            Darken("editor.background", 0.05),
+        ),
+        ("editorCursor.foreground", Hex("#aeafad"), Hex("#000000")),
+        (
+            "editorWhitespace.foreground",
+            Hex("#e3e4e229"),
+            Hex("#33333333"),
+        ),
+        ("editor.findMatchBackground", Hex("#515c6a"), Hex("#a8ac94")),
+        (
+            "editor.findMatchHighlightBackground",
+            Hex("#ea5c0055"),
+            Hex("#ea5c0055"),
+        ),
+        (
+            "editor.snippetTabstopHighlightBackground",
+            Hex("#7c7c7c4d"),
+            Hex("#0a326433"),
+        ),
+        (
+            "editorBracketMatch.background",
+            Hex("#0064001a"),
+            Hex("#0064001a"),
+        ),
+        // VSCode leaves the last three bracket colors transparent, and cycles
+        // through the first three instead
+        (
+            "editorBracketHighlight.foreground1",
+            Hex("#ffd700"),
+            Hex("#0431fa"),
+        ),
+        (
+            "editorBracketHighlight.foreground2",
+            Hex("#da70d6"),
+            Hex("#319331"),
+        ),
+        (
+            "editorBracketHighlight.foreground3",
+            Hex("#179fff"),
+            Hex("#7b3814"),
+        ),
+        (
+            "editorBracketHighlight.foreground4",
+            Key("editorBracketHighlight.foreground1"),
+            Key("editorBracketHighlight.foreground1"),
+        ),
+        (
+            "editorBracketHighlight.foreground5",
+            Key("editorBracketHighlight.foreground2"),
+            Key("editorBracketHighlight.foreground2"),
+        ),
+        (
+            "editorBracketHighlight.foreground6",
+            Key("editorBracketHighlight.foreground3"),
+            Key("editorBracketHighlight.foreground3"),
+        ),
+        ("editorError.foreground", Hex("#f14c4c"), Hex("#e51400")),
+        ("editorWarning.foreground", Hex("#cca700"), Hex("#bf8803")),
+        ("editorInfo.foreground", Hex("#3794ff"), Hex("#1a85ff")),
+        ("editorHint.foreground", Hex("#eeeeeeb3"), Hex("#6c6c6c")),
+        ("textLink.foreground", Hex("#3794ff"), Hex("#006ab1")),
+        (
+            "editorGutter.background",
+            Key("editor.background"),
+            Key("editor.background"),
+        ),
+        (
+            "editorLineNumber.foreground",
+            Transparent("editor.foreground", 0.6),
+            Transparent("editor.foreground", 0.6),
+        ),
+        (
+            "editorLineNumber.activeForeground",
+            Key("editor.foreground"),
+            Key("editor.foreground"),
+        ),
+        (
+            "editorLineNumber.background",
+            Key("editor.background"),
+            Key("editor.background"),
+        ),
+        // Tabs
+        (
+            "editorGroupHeader.tabsBackground",
+            Darken("editor.background", 0.2),
+            Darken("editor.background", 0.05),
+        ),
+        (
+            "tab.activeBackground",
+            Key("editor.background"),
+            Key("editor.background"),
+        ),
+        (
+            "tab.activeForeground",
+            Key("editor.foreground"),
+            Key("editor.foreground"),
+        ),
+        (
+            "tab.inactiveBackground",
+            Key("editorGroupHeader.tabsBackground"),
+            Key("editorGroupHeader.tabsBackground"),
+        ),
+        (
+            "tab.inactiveForeground",
+            Transparent("tab.activeForeground", 0.5),
+            Transparent("tab.activeForeground", 0.7),
+        ),
+        // Status bar
+        ("statusBar.background", Hex("#007acc"), Hex("#007acc")),
+        ("statusBar.foreground", Hex("#ffffff"), Hex("#ffffff")),
+        // Window borders, panels and breadcrumbs
+        ("editorGroup.border", Hex("#444444"), Hex("#e7e7e7")),
+        ("panel.border", Hex("#80808059"), Hex("#80808059")),
+        (
+            "panelTitle.activeForeground",
+            Hex("#e7e7e7"),
+            Hex("#424242"),
+        ),
+        (
+            "breadcrumb.background",
+            Key("editor.background"),
+            Key("editor.background"),
+        ),
+        (
+            "breadcrumb.foreground",
+            Transparent("editor.foreground", 0.8),
+            Transparent("editor.foreground", 0.8),
+        ),
+        // Widgets and lists
+        ("editorWidget.background", Hex("#252526"), Hex("#f3f3f3")),
+        (
+            "editorWidget.foreground",
+            Key("editor.foreground"),
+            Key("editor.foreground"),
+        ),
+        ("editorWidget.border", Hex("#454545"), Hex("#c8c8c8")),
+        (
+            "list.activeSelectionBackground",
+            Hex("#04395e"),
+            Hex("#0060c0"),
+        ),
+        (
+            "list.activeSelectionForeground",
+            Hex("#ffffff"),
+            Hex("#ffffff"),
+        ),
+        (
+            "scrollbarSlider.background",
+            Hex("#79797966"),
+            Hex("#64646466"),
+        ),
+        (
+            "editorSuggestWidget.background",
+            Key("editorWidget.background"),
+            Key("editorWidget.background"),
+        ),
+        (
+            "editorSuggestWidget.foreground",
+            Key("editor.foreground"),
+            Key("editor.foreground"),
+        ),
+        (
+            "editorSuggestWidget.selectedBackground",
+            Key("list.activeSelectionBackground"),
+            Key("list.activeSelectionBackground"),
+        ),
+        (
+            "editorSuggestWidget.selectedForeground",
+            Key("list.activeSelectionForeground"),
+            Key("list.activeSelectionForeground"),
+        ),
+        (
+            "editorSuggestWidget.highlightForeground",
+            Hex("#2aaaff"),
+            Hex("#0066bf"),
+        ),
+        (
+            "editorSuggestWidget.focusHighlightForeground",
+            Key("editorSuggestWidget.highlightForeground"),
+            Key("editorSuggestWidget.highlightForeground"),
+        ),
+        // Diffs
+        (
+            "diffEditor.insertedTextBackground",
+            Hex("#9ccc2c33"),
+            Hex("#9ccc2c40"),
+        ),
+        (
+            "diffEditor.removedTextBackground",
+            Hex("#ff000033"),
+            Hex("#ff000033"),
+        ),
+        (
+            "diffEditor.insertedLineBackground",
+            Key("diffEditor.insertedTextBackground"),
+            Key("diffEditor.insertedTextBackground"),
+        ),
+        (
+            "diffEditor.removedLineBackground",
+            Key("diffEditor.removedTextBackground"),
+            Key("diffEditor.removedTextBackground"),
+        ),
+        // Gutter and git decorations
+        (
+            "editorGutter.addedBackground",
+            Hex("#2ea043"),
+            Hex("#48985d"),
+        ),
+        (
+            "editorGutter.modifiedBackground",
+            Hex("#0c7d9d"),
+            Hex("#2090d3"),
+        ),
+        (
+            "editorGutter.deletedBackground",
+            Hex("#f14c4c"),
+            Hex("#e51400"),
+        ),
+        (
+            "gitDecoration.addedResourceForeground",
+            Hex("#81b88b"),
+            Hex("#587c0c"),
+        ),
+        (
+            "gitDecoration.modifiedResourceForeground",
+            Hex("#e2c08d"),
+            Hex("#895503"),
+        ),
+        (
+            "gitDecoration.deletedResourceForeground",
+            Hex("#c74e39"),
+            Hex("#ad0707"),
+        ),
+    ]
+}
+
+/// Returns the colors of `theme`, with every color djanho maps that the theme
+/// doesn't define derived the way VSCode would.
+pub fn with_defaults(theme: &VSCodeTheme) -> HashMap<String, String> {
+    let mut colors = theme.colors.clone().unwrap_or_default();
+    let light = is_light(theme, &colors);
+
+    for (key, dark_default, light_default) in defaults() {
+        if colors.contains_key(key) {
+            continue;
+        }
+        let derivation = if light { light_default } else { dark_default };
+        if let Some(color) = derive(derivation, &colors) {
+            colors.insert(key.to_string(), color);
+        }
+    }
+
+    colors
+}
+
+/// Returns, for every color djanho derives, the key of `theme` it is derived
+/// from. Colors derived from fixed defaults alone are left out.
+pub fn origins(theme: &VSCodeTheme) -> HashMap<&'static str, String> {
+    let colors = theme.colors.clone().unwrap_or_default();
+    let light = is_light(theme, &colors);
+    let mut origins: HashMap<&'static str, String> = HashMap::new();
+
+    for (key, dark_default, light_default) in defaults() {
+        if colors.contains_key(key) {
+            continue;
+        }
+        let derivation = if light { light_default } else { dark_default };
+        let source = match derivation {
+            Hex(_) => continue,
+            Key(source) | Transparent(source, _) | Darken(source, _) | Lighten(source, _) => source,
+        };
+        let origin = if colors.contains_key(source) {
+            Some(source.to_string())
+        } else {
+            origins.get(source).cloned()
+        };
+        if let Some(origin) = origin {
+            origins.insert(key, origin);
+        }
+    }
+
+    origins
+}
+
+/// Whether the theme is light, by its `type` or by its background
+fn is_light(theme: &VSCodeTheme, colors: &HashMap<String, String>) -> bool {
+    match theme.kind.as_deref() {
+        Some("light") | Some("hc-light") => true,
+        Some(_) => false,
+        None => colors
+            .get("editor.background")
+            .and_then(|color| colors::parse(color).ok())
+            .map(colors::is_light)
+            .unwrap_or(false),
+    }
+}
+
+fn derive(derivation: Derivation, colors: &HashMap<String, String>) -> Option<String> {
+    let parse = |key: &str| colors.get(key).and_then(|color| colors::parse(color).ok());
+    let scale_lightness = |color: RGBA, factor: f64| {
+        let hsl = colors::to_hsl(color);
+        let l = (hsl.l + hsl.l * factor).clamp(0.0, 1.0);
+        RGBA {
+            a: color.a,
+            ..colors::from_hsl(HSL { l, ..hsl })
+        }
+    };
+
+    let color = match derivation {
+        Hex(hex) => return Some(hex.to_string()),
+        Key(key) => return colors.get(key).cloned(),
+        Transparent(key, factor) => {
+            let color = parse(key)?;
+            RGBA {
+                a: color.a * factor,
+                ..color
+            }
+        }
+        Darken(key, factor) => scale_lightness(parse(key)?, -factor),
+        Lighten(key, factor) => scale_lightness(parse(key)?, factor),
+    };
+
+    Some(colors::to_rgba_hex_string(color))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decoder;
+
+    fn theme(json: &str) -> VSCodeTheme {
+        decoder::parse_reader(json.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn can_derive_colors() {
+        let colors: HashMap<String, String> = vec![("base", "#808080"), ("red", "#ff0000")]
+            .into_iter()
+            .map(|(key, color)| (key.to_string(), color.to_string()))
+            .collect();
+        let derive = |derivation| derive(derivation, &colors);
+
+        assert_eq!(derive(Hex("#123456")).as_deref(), Some("#123456"));
+        assert_eq!(derive(Key("base")).as_deref(), Some("#808080"));
+        assert_eq!(
+            derive(Transparent("red", 0.5)).as_deref(),
+            Some("#ff000080")
+        );
+        assert_eq!(derive(Darken("base", 0.5)).as_deref(), Some("#404040"));
+        assert_eq!(derive(Lighten("base", 0.5)).as_deref(), Some("#c0c0c0"));
+        assert_eq!(derive(Key("missing")), None);
+        assert_eq!(derive(Darken("missing", 0.5)), None);
+    }
+
+    #[test]
+    fn can_pick_light_or_dark_defaults() {
+        let dark = with_defaults(&theme(r#"{ "type": "dark" }"#));
+        assert_eq!(dark["editor.background"], "#1e1e1e");
+        assert_eq!(dark["editor.foreground"], "#d4d4d4");
+
+        let light = with_defaults(&theme(r#"{ "type": "light" }"#));
+        assert_eq!(light["editor.background"], "#ffffff");
+        assert_eq!(light["editor.foreground"], "#000000");
+
+        // Untyped themes are light when their background is
+        let untyped = with_defaults(&theme(
+            r##"{ "colors": { "editor.background": "#fafafa" } }"##,
+        ));
+        assert_eq!(untyped["editor.foreground"], "#000000");
+        assert_eq!(untyped["editor.lineHighlightBackground"], "#ededed");
+    }
+}
@@ -0,0 +1,113 @@
+use std::{fmt, io, path::PathBuf};
+
+/// Errors that can happen while loading or converting a theme
+#[derive(Debug)]
+pub enum Error {
+    /// A file, or the reader a theme was decoded from, could not be read
+    Io {
+        path: Option<PathBuf>,
+        source: io::Error,
+    },
+    /// The theme, patch or settings (as told by `kind`) are not valid JSON
+    Json {
+        kind: &'static str,
+        source: serde_json::Error,
+    },
+    /// The theme ends up including itself
+    IncludeCycle(PathBuf),
+    /// A TOML patch could not be decoded
+    Toml(toml::de::Error),
+    /// An entry of a patch or settings, at `key`, doesn't have the shape
+    /// VSCode expects
+    InvalidPatch {
+        key: String,
+        source: serde_json::Error,
+    },
+    /// Options that can't be used together, such as synthesizing a variant
+    /// when both were given
+    ConflictingOptions(&'static str),
+    /// A colorscheme name that can't be used in file names, such as one with
+    /// path separators
+    InvalidName(String),
+    /// A generated file wouldn't load
+    InvalidOutput { path: PathBuf, message: String },
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io {
+                path: Some(path),
+                source,
+            } => write!(f, "Could not read {}: {}", path.display(), source),
+            Error::Io { path: None, source } => write!(f, "Could not read the input: {}", source),
+            Error::Json { kind, source } => write!(f, "Could not decode the {}: {}", kind, source),
+            Error::IncludeCycle(path) => {
+                write!(f, "The theme {} includes itself", path.display())
+            }
+            Error::Toml(err) => write!(f, "Could not decode the patch: {}", err),
+            Error::InvalidPatch { key, source } => write!(f, "Invalid {}: {}", key, source),
+            Error::ConflictingOptions(message) => write!(f, "{}", message),
+            Error::InvalidName(name) => write!(f, "Invalid colorscheme name: {}", name),
+            Error::InvalidOutput { path, message } => {
+                write!(
+                    f,
+                    "The generated {} wouldn't load: {}",
+                    path.display(),
+                    message
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io { source, .. } => Some(source),
+            Error::Json { source, .. } => Some(source),
+            Error::IncludeCycle(_) => None,
+            Error::Toml(err) => Some(err),
+            Error::InvalidPatch { source, .. } => Some(source),
+            Error::ConflictingOptions(_) => None,
+            Error::InvalidName(_) => None,
+            Error::InvalidOutput { .. } => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Error {
+        Error::Io {
+            path: None,
+            source: err,
+        }
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Error {
+        Error::Json {
+            kind: "theme",
+            source: err,
+        }
+    }
+}
+
+impl Error {
+    /// Wraps an error that happened while reading the file at `path`
+    pub fn io<P: Into<PathBuf>>(path: P) -> impl FnOnce(io::Error) -> Error {
+        let path = path.into();
+        move |source| Error::Io {
+            path: Some(path),
+            source,
+        }
+    }
+
+    /// Wraps an error decoding the JSON of a `kind` of input
+    pub fn json(kind: &'static str) -> impl FnOnce(serde_json::Error) -> Error {
+        move |source| Error::Json { kind, source }
+    }
+}
@@ -1,10 +1,22 @@
-use crate::highlights::VimHighlight;
+use crate::ir::ResolvedTheme;
 
 // Use this for Lua and Vimscript generators
 pub trait ConfigGenerator {
-    fn collect(&self) -> String;
-    fn link(&mut self, group: &str, target: &str);
-    fn highlight(&mut self, options: &VimHighlight);
-    fn variable(&mut self, name: String, color: String);
-    fn newline(&mut self);
+    fn render(&self, theme: &ResolvedTheme) -> String;
+    /// Renders a colorscheme that switches between both variants according
+    /// to `background`. Their palettes must be shared with `ir::share_palette`.
+    fn render_pair(&self, dark: &ResolvedTheme, light: &ResolvedTheme) -> String;
+}
+
+/// Indents every non-empty line of `text` by `width` spaces
+pub fn indent(text: &str, width: usize) -> String {
+    text.lines()
+        .map(|line| {
+            if line.is_empty() {
+                "\n".to_string()
+            } else {
+                format!("{}{}\n", " ".repeat(width), line)
+            }
+        })
+        .collect()
 }
@@ -3,10 +3,12 @@
 use crate::{
     colors,
     decoder::{self, VSCodeScope},
-    generator::ConfigGenerator,
-    highlights::{self, VimHighlight},
+    defaults,
+    highlights::{self, map_font_styles, Source, VimHighlight, REVERSED_GROUPS},
+    ir::{self, InvalidColor, Metadata, PaletteColor, ResolvedTheme, VimLink},
+    languages,
 };
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 /// HashMap containing hex colors as keys, generated highlight groups as values
 type UsedColors = HashMap<String, String>;
@@ -27,11 +29,15 @@ fn create_group(idx: i32) -> String {
     format!("Color{}", idx)
 }
 
-pub fn generate_config(theme: decoder::VSCodeTheme, generator: &mut Box<dyn ConfigGenerator>) {
+/// Resolves the colors, scopes and links of a VSCode theme into a `ResolvedTheme`
+pub fn resolve(theme: &decoder::VSCodeTheme) -> ResolvedTheme {
     let highlights = highlights::highlights();
+    let ui_colors = defaults::with_defaults(theme);
+    let origins = defaults::origins(theme);
     let mut used_colors: UsedColors = HashMap::new();
+    let mut palette: Vec<PaletteColor> = Vec::new();
     let mut parsed_highlights: Vec<VimHighlight> = Vec::new();
-    let mut links: HashMap<&str, &str> = highlights.links.into_iter().collect();
+    let mut links: BTreeMap<&str, &str> = highlights.links.into_iter().collect();
     let mut color_index = 0;
     let mut background_color = colors::RGBA {
         r: 0,
@@ -41,34 +47,53 @@ pub fn generate_config(theme: decoder::VSCodeTheme, generator: &mut Box<dyn Conf
     };
 
     // Find the background color
-    if let Some(colors) = &theme.colors {
-        for (option, color) in colors {
-            if option == "editor.background" {
-                if let Ok(rgba) = colors::from_hex_string(color) {
-                    background_color = rgba;
-                }
-            }
+    if let Some(color) = ui_colors.get("editor.background") {
+        if let Ok(rgba) = colors::parse(color) {
+            background_color = rgba;
         }
     }
 
     // Parse token highlight colors
     for theme_token in &theme.tokens {
         for highlight_token in &highlights.tokens {
-            map_scopes!(&theme_token.scope, |scope| {
+            map_scopes!(&theme_token.scope, |scope: &String| {
                 if highlight_token.0 == scope {
                     let (bg_group, fg_group) = parse_differences_and_add_to_hashmap(
                         &mut used_colors,
+                        &mut palette,
                         &mut color_index,
                         &theme_token.settings.background,
                         &theme_token.settings.foreground,
                         background_color,
                     );
-                    parsed_highlights.push(VimHighlight {
-                        group: highlight_token.1.to_string(),
-                        background: bg_group,
-                        foreground: fg_group,
-                        text_style: theme_token.settings.font_style.clone(),
-                    });
+                    let source = Source::Scope(scope.to_string());
+                    let group = highlight_token.1;
+                    // Later rules override the settings they define, like in VSCode
+                    match parsed_highlights.iter_mut().find(|h| h.group == group) {
+                        Some(highlight) => {
+                            if bg_group.is_some() {
+                                highlight.background = bg_group;
+                            }
+                            if fg_group.is_some() {
+                                highlight.foreground = fg_group;
+                            }
+                            if theme_token.settings.font_style.is_some() {
+                                highlight.text_style =
+                                    map_font_styles(&theme_token.settings.font_style);
+                            }
+                            if !highlight.sources.contains(&source) {
+                                highlight.sources.push(source);
+                            }
+                        }
+                        None => parsed_highlights.push(VimHighlight {
+                            group: group.to_string(),
+                            background: bg_group,
+                            foreground: fg_group,
+                            special: None,
+                            text_style: map_font_styles(&theme_token.settings.font_style),
+                            sources: vec![source],
+                        }),
+                    }
                 }
                 if let Some(fallback) = highlight_token.2 {
                     links.insert(highlight_token.1, fallback);
@@ -77,70 +102,229 @@ pub fn generate_config(theme: decoder::VSCodeTheme, generator: &mut Box<dyn Conf
         }
     }
 
-    // Parse UI colors
-    if let Some(colors) = &theme.colors {
-        for highlight_color in &highlights.colors {
-            let (mut background, mut foreground) = (None, None);
-
-            for (option, color) in colors {
-                if let Some(background_option) = highlight_color.1 {
-                    if background_option == option {
-                        let (bg_group, _) = parse_differences_and_add_to_hashmap(
-                            &mut used_colors,
-                            &mut color_index,
-                            &Some(color.to_string()),
-                            &None,
-                            background_color,
-                        );
-                        background = bg_group;
-                    }
-                }
-
-                if let Some(foreground_option) = highlight_color.2 {
-                    if foreground_option == option {
-                        let (_, fg_group) = parse_differences_and_add_to_hashmap(
-                            &mut used_colors,
-                            &mut color_index,
-                            &None,
-                            &Some(color.to_string()),
-                            background_color,
-                        );
-                        foreground = fg_group;
+    // Parse language-specific scopes, which take the settings of the most
+    // specific theme rules applying to them
+    for language in languages::languages() {
+        for (scope, group) in language.tokens {
+            if let Some((settings, selectors)) = languages::settings_of(&theme.tokens, scope) {
+                let (bg_group, fg_group) = parse_differences_and_add_to_hashmap(
+                    &mut used_colors,
+                    &mut palette,
+                    &mut color_index,
+                    &settings.background,
+                    &settings.foreground,
+                    background_color,
+                );
+                let sources = selectors
+                    .into_iter()
+                    .map(|selector| Source::Scope(selector.to_string()));
+                // Languages sharing a group refine it, like later token rules
+                match parsed_highlights.iter_mut().find(|h| h.group == group) {
+                    Some(highlight) => {
+                        if bg_group.is_some() {
+                            highlight.background = bg_group;
+                        }
+                        if fg_group.is_some() {
+                            highlight.foreground = fg_group;
+                        }
+                        if settings.font_style.is_some() {
+                            highlight.text_style = map_font_styles(&settings.font_style);
+                        }
+                        for source in sources {
+                            if !highlight.sources.contains(&source) {
+                                highlight.sources.push(source);
+                            }
+                        }
                     }
+                    None => parsed_highlights.push(VimHighlight {
+                        group: group.to_string(),
+                        background: bg_group,
+                        foreground: fg_group,
+                        special: None,
+                        text_style: map_font_styles(&settings.font_style),
+                        sources: sources.collect(),
+                    }),
                 }
             }
+        }
+    }
 
-            if let (None, None) = (&foreground, &background) {
-                continue;
-            } else {
-                parsed_highlights.push(VimHighlight {
-                    group: highlight_color.0.to_string(),
-                    background,
-                    foreground,
-                    text_style: None,
-                })
-            }
+    // Parse semantic token colors, which Neovim applies to the @lsp groups
+    for (selector, style) in &theme.semantic_tokens {
+        let group = match highlights::semantic_group(selector) {
+            Some(group) => group,
+            None => continue,
+        };
+        let settings = style.settings();
+        let (_, fg_group) = parse_differences_and_add_to_hashmap(
+            &mut used_colors,
+            &mut palette,
+            &mut color_index,
+            &None,
+            &settings.foreground,
+            background_color,
+        );
+        let text_style = map_font_styles(&settings.font_style);
+        if fg_group.is_none() && text_style.is_none() {
+            continue;
+        }
+        parsed_highlights.push(VimHighlight {
+            group,
+            background: None,
+            foreground: fg_group,
+            special: None,
+            text_style,
+            sources: vec![Source::Semantic(selector.clone())],
+        });
+    }
+
+    // Parse UI colors
+    for highlight_color in &highlights.colors {
+        let background = highlight_color.1.and_then(|option| ui_colors.get(option));
+        let foreground = highlight_color.2.and_then(|option| ui_colors.get(option));
+        let (background, foreground) = parse_differences_and_add_to_hashmap(
+            &mut used_colors,
+            &mut palette,
+            &mut color_index,
+            &background.cloned(),
+            &foreground.cloned(),
+            background_color,
+        );
+
+        if let (None, None) = (&foreground, &background) {
+            continue;
+        } else {
+            parsed_highlights.push(VimHighlight {
+                group: highlight_color.0.to_string(),
+                background,
+                foreground,
+                special: None,
+                text_style: if REVERSED_GROUPS.contains(&highlight_color.0) {
+                    Some("NONE".to_string())
+                } else {
+                    None
+                },
+                sources: color_sources(theme, &origins, &[highlight_color.1, highlight_color.2]),
+            })
+        }
+    }
+
+    // Parse undercurls, which are colored by their special color alone
+    for (group, key) in &highlights.undercurls {
+        let (_, special) = parse_differences_and_add_to_hashmap(
+            &mut used_colors,
+            &mut palette,
+            &mut color_index,
+            &None,
+            &ui_colors.get(*key).cloned(),
+            background_color,
+        );
+
+        if special.is_some() {
+            parsed_highlights.push(VimHighlight {
+                group: group.to_string(),
+                background: None,
+                foreground: None,
+                special,
+                text_style: Some("undercurl".to_string()),
+                sources: color_sources(theme, &origins, &[Some(*key)]),
+            })
         }
     }
 
-    generator.newline();
-    for (color_hex, highlight_group) in &used_colors {
-        generator.variable(highlight_group.to_string(), color_hex.to_string())
+    // Links are fallbacks, so groups the theme colors aren't linked
+    links.retain(|group, _| {
+        !parsed_highlights
+            .iter()
+            .any(|highlight| highlight.group == *group && highlight.has_attributes())
+    });
+
+    ResolvedTheme {
+        metadata: Metadata {
+            name: theme.name.clone(),
+            kind: theme.kind.clone(),
+            background: colors::to_rgb_hex_string(background_color),
+            synthesized_from: None,
+        },
+        palette,
+        highlights: parsed_highlights,
+        links: links
+            .into_iter()
+            .map(|(group, target)| VimLink {
+                group: group.to_string(),
+                target: target.to_string(),
+            })
+            .collect(),
+        invalid_colors: invalid_colors(theme),
     }
+}
 
-    generator.newline();
-    for highlight in parsed_highlights {
-        generator.highlight(&highlight)
+/// Parses every color of the theme, collecting the ones that are left out
+/// because they can't be parsed
+fn invalid_colors(theme: &decoder::VSCodeTheme) -> Vec<InvalidColor> {
+    let mut colors: Vec<(String, &String)> = Vec::new();
+    if let Some(theme_colors) = &theme.colors {
+        for (key, color) in theme_colors {
+            colors.push((format!("colors[{:?}]", key), color));
+        }
+        colors.sort();
+    }
+    for (idx, token) in theme.tokens.iter().enumerate() {
+        let settings = &token.settings;
+        if let Some(color) = &settings.foreground {
+            colors.push((format!("tokenColors[{}].foreground", idx), color));
+        }
+        if let Some(color) = &settings.background {
+            colors.push((format!("tokenColors[{}].background", idx), color));
+        }
+    }
+    for (selector, style) in &theme.semantic_tokens {
+        let color = match style {
+            decoder::VSCodeSemanticStyle::Color(color) => Some(color),
+            decoder::VSCodeSemanticStyle::Style(settings) => settings.foreground.as_ref(),
+        };
+        if let Some(color) = color {
+            colors.push((format!("semanticTokenColors[{:?}]", selector), color));
+        }
     }
 
-    generator.newline();
-    for (group, target_group) in links {
-        generator.link(group, target_group);
+    colors
+        .into_iter()
+        .filter_map(|(location, color)| {
+            colors::parse(color)
+                .err()
+                .map(|error| InvalidColor { location, error })
+        })
+        .collect()
+}
+
+/// The keys of `theme` the given UI colors come from, either directly or by
+/// being derived from them
+fn color_sources(
+    theme: &decoder::VSCodeTheme,
+    origins: &HashMap<&'static str, String>,
+    keys: &[Option<&str>],
+) -> Vec<Source> {
+    let theme_colors = theme.colors.as_ref();
+    let mut sources = Vec::new();
+    for key in keys.iter().flatten() {
+        let origin = if theme_colors.is_some_and(|colors| colors.contains_key(*key)) {
+            Some(key.to_string())
+        } else {
+            origins.get(key).cloned()
+        };
+        if let Some(source) = origin.map(Source::Color) {
+            if !sources.contains(&source) {
+                sources.push(source);
+            }
+        }
     }
+    sources
 }
 
 fn parse_differences_and_add_to_hashmap(
-    used_colors: &mut HashMap<String, String>,
+    used_colors: &mut UsedColors,
+    palette: &mut Vec<PaletteColor>,
     color_idx: &mut i32,
     background: &Option<String>,
     foreground: &Option<String>,
@@ -148,25 +332,37 @@ fn parse_differences_and_add_to_hashmap(
 ) -> (Option<String>, Option<String>) {
     let mut result: (Option<String>, Option<String>) = (None, None);
 
+    let background = background
+        .as_ref()
+        .and_then(|color| parse_color(color, background_color, 1.0));
     if let Some(background) = background {
-        let background = parse_color(background.clone(), background_color, 1.0);
         if let Some(color_group) = used_colors.get(&background) {
             result.0 = Some(color_group.clone());
         } else {
             let group = create_group(*color_idx);
-            used_colors.insert(background, group.clone());
+            used_colors.insert(background.clone(), group.clone());
+            palette.push(PaletteColor {
+                name: group.clone(),
+                color: background,
+            });
             result.0 = Some(group);
             *color_idx += 1;
         }
     }
 
+    let foreground = foreground
+        .as_ref()
+        .and_then(|color| parse_color(color, background_color, 1.0));
     if let Some(foreground) = foreground {
-        let foreground = parse_color(foreground.clone(), background_color, 1.0);
         if let Some(color_group) = used_colors.get(&foreground) {
             result.1 = Some(color_group.clone());
         } else {
             let group = create_group(*color_idx);
-            used_colors.insert(foreground, group.clone());
+            used_colors.insert(foreground.clone(), group.clone());
+            palette.push(PaletteColor {
+                name: group.clone(),
+                color: foreground,
+            });
             result.1 = Some(group);
             *color_idx += 1;
         }
@@ -175,17 +371,72 @@ fn parse_differences_and_add_to_hashmap(
     result
 }
 
-fn parse_color(color: String, bg_color: colors::RGBA, scaler: f32) -> String {
-    if !colors::is_rgba(color.as_str()) {
-        return color;
-    }
-
-    // If the color is RGBA, we blend it with the background
-    if let Ok(colors::RGBA { r, g, b, a }) = colors::from_hex_string(color.as_str()) {
-        let mut color = colors::blend(bg_color, colors::RGBA { r, g, b, a });
+/// Normalizes a theme color to a hex string, or `None` when it can't be parsed
+fn parse_color(color: &str, bg_color: colors::RGBA, scaler: f32) -> Option<String> {
+    let rgba = colors::parse(color).ok()?;
+    // Fully transparent colors unset the attribute, and colors with some
+    // transparency are blended with the background
+    if rgba.a == 0.0 {
+        Some(ir::NONE.to_string())
+    } else if rgba.a < 1.0 {
+        let mut color = colors::blend(bg_color, rgba);
         colors::scale(&mut color, scaler);
-        colors::to_rgb_hex_string(color)
+        Some(colors::to_rgb_hex_string(color))
     } else {
-        color.to_string()
+        Some(colors::to_rgb_hex_string(rgba))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        compiled::CompiledGenerator, generator::ConfigGenerator, lua::LuaGenerator, theme::Theme,
+        vimscript::VimscriptGenerator,
+    };
+
+    #[test]
+    fn can_map_status_and_tab_lines_unreversed() {
+        let theme: Theme = r##"{
+            "colors": {
+                "statusBar.background": "#111111",
+                "statusBar.foreground": "#eeeeee",
+                "tab.activeBackground": "#222222",
+                "tab.activeForeground": "#dddddd"
+            }
+        }"##
+        .parse()
+        .unwrap();
+        let resolved = theme.resolve();
+        let name = |hex: &str| {
+            let entry = resolved.palette.iter().find(|entry| entry.color == hex);
+            entry.unwrap().name.clone()
+        };
+        let (status_bg, status_fg) = (name("#111111"), name("#eeeeee"));
+        let (tab_bg, tab_fg) = (name("#222222"), name("#dddddd"));
+
+        // Vim reverses StatusLine by default, which `NONE` clears
+        let vimscript = VimscriptGenerator::new("fixture").render(&resolved);
+        assert!(vimscript.contains(&format!(
+            "call s:highlight('StatusLine', s:{}, s:{}, 'NONE')",
+            status_bg, status_fg
+        )));
+        assert!(vimscript.contains(&format!(
+            "call s:highlight('TabLineSel', s:{}, s:{}, '')",
+            tab_bg, tab_fg
+        )));
+
+        let lua = LuaGenerator::new("fixture").render(&resolved);
+        assert!(lua.contains(&format!(
+            "highlight('StatusLine', palette.{}, palette.{}, 'NONE')",
+            status_bg, status_fg
+        )));
+        assert!(lua.contains(&format!(
+            "highlight('TabLineSel', palette.{}, palette.{}, nil)",
+            tab_bg, tab_fg
+        )));
+
+        let compiled = CompiledGenerator::new("fixture").render(&resolved);
+        assert!(compiled.contains("set_hl(ns, 'StatusLine', { fg = '#eeeeee', bg = '#111111' })"));
+        assert!(compiled.contains("set_hl(ns, 'TabLineSel', { fg = '#dddddd', bg = '#222222' })"));
     }
 }
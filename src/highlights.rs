@@ -1,7 +1,11 @@
+use serde::Serialize;
+
 /// A tuple containing (VSCode Token, Vim target highlight, fallback group)
 type VSCodeToken = (&'static str, &'static str, Option<&'static str>);
-/// A tuple containing (Vim target, VSCode UI FG, VSCode UI BG)
+/// A tuple containing (Vim target, VSCode UI BG, VSCode UI FG)
 pub type VSCodeColor = (&'static str, Option<&'static str>, Option<&'static str>);
+/// A tuple containing (Vim target, VSCode UI color of the undercurl)
+pub type VSCodeUndercurl = (&'static str, &'static str);
 /// A tuple containing (Vim target group, Vim source group)
 type VimLink = (&'static str, &'static str);
 
@@ -9,6 +13,7 @@ type VimLink = (&'static str, &'static str);
 pub struct Highlight {
     pub tokens: Vec<VSCodeToken>,
     pub colors: Vec<VSCodeColor>,
+    pub undercurls: Vec<VSCodeUndercurl>,
     pub links: Vec<VimLink>,
 }
 
@@ -26,10 +31,10 @@ pub fn highlights() -> Highlight {
             ("entity.name.function.macro", "Macro", None),
             ("number", "Number", None),
             ("constant.numeric", "Number", None),
-            ("brackethighlighter.tag", "MyTag", None),
-            ("brackethighlighter.angle", "MyTag", None),
-            ("brackethighlighter.round", "MyTag", None),
-            ("brackethighlighter.square", "MyTag", None),
+            ("brackethighlighter.tag", "Tag", None),
+            ("brackethighlighter.angle", "Delimiter", None),
+            ("brackethighlighter.round", "Delimiter", None),
+            ("brackethighlighter.square", "Delimiter", None),
             ("entity.name.function", "Function", None),
             ("function", "Function", None),
             ("keyword.operator", "Operator", Some("Keyword")),
@@ -74,44 +79,150 @@ pub fn highlights() -> Highlight {
                 "TSVariableBuiltin",
                 None,
             ),
-            // -- TSText               { };    -- For strings considered text in a markup language.
-            // -- TSEmphasis           { };    -- For text to be represented with emphasis.
-            // -- TSUnderline          { };    -- For text to be represented with an underline.
-            // -- TSStrike             { };    -- For strikethrough text.
-            // -- TSTitle              { };    -- Text that is part of a title.
-            // -- TSLiteral            { };    -- Literal text.
-            // -- TSURI                { };    -- Any URI like a link or email.
+            // Markup
+            ("markup.heading", "TSTitle", None),
+            ("markup.heading", "@markup.heading", None),
+            ("markup.bold", "TSStrong", None),
+            ("markup.bold", "htmlBold", None),
+            ("markup.bold", "@markup.strong", None),
+            ("markup.italic", "TSEmphasis", None),
+            ("markup.italic", "htmlItalic", None),
+            ("markup.italic", "@markup.italic", None),
+            ("markup.underline", "TSUnderline", None),
+            ("markup.underline", "htmlUnderline", None),
+            ("markup.underline", "@markup.underline", None),
+            ("markup.strikethrough", "TSStrike", None),
+            ("markup.strikethrough", "htmlStrike", None),
+            ("markup.strikethrough", "@markup.strikethrough", None),
+            ("markup.underline.link", "TSURI", None),
+            ("markup.underline.link", "Underlined", None),
+            ("markup.underline.link", "htmlLink", None),
+            ("markup.underline.link", "@markup.link.url", None),
+            ("markup.inline.raw", "TSLiteral", None),
+            ("markup.inline.raw", "@markup.raw", None),
+            ("markup.fenced_code.block", "@markup.raw.block", None),
+            ("markup.quote", "@markup.quote", None),
+            ("markup.list", "@markup.list", None),
+            ("markup.inserted", "diffAdded", None),
+            ("markup.inserted", "@diff.plus", None),
+            ("markup.changed", "diffChanged", None),
+            ("markup.changed", "@diff.delta", None),
+            ("markup.deleted", "diffRemoved", None),
+            ("markup.deleted", "@diff.minus", None),
         ],
         colors: vec![
             (
                 "StatusLine",
-                Some("statusBar.foreground"),
                 Some("statusBar.background"),
+                Some("statusBar.foreground"),
+            ),
+            (
+                "StatusLineNC",
+                Some("editorGroupHeader.tabsBackground"),
+                Some("tab.inactiveForeground"),
             ),
             (
                 "WildMenu",
                 Some("editor.background"),
                 Some("editor.foreground"),
             ),
+            ("WinSeparator", None, Some("editorGroup.border")),
+            ("MsgSeparator", None, Some("panel.border")),
+            (
+                "WinBar",
+                Some("breadcrumb.background"),
+                Some("breadcrumb.foreground"),
+            ),
+            (
+                "WinBarNC",
+                Some("breadcrumb.background"),
+                Some("tab.inactiveForeground"),
+            ),
             // Popup menu
             (
                 "Pmenu",
-                Some("editor.background"),
-                Some("editor.foreground"),
+                Some("editorSuggestWidget.background"),
+                Some("editorSuggestWidget.foreground"),
             ),
             (
                 "PmenuSel",
-                Some("editor.foreground"),
-                Some("tab.activeBackground"),
+                Some("editorSuggestWidget.selectedBackground"),
+                Some("editorSuggestWidget.selectedForeground"),
             ),
             (
-                "PmenuThumb",
-                Some("editor.background"),
-                Some("editor.foreground"),
+                "PmenuMatch",
+                None,
+                Some("editorSuggestWidget.highlightForeground"),
+            ),
+            (
+                "PmenuMatchSel",
+                None,
+                Some("editorSuggestWidget.focusHighlightForeground"),
+            ),
+            ("PmenuSbar", Some("editorSuggestWidget.background"), None),
+            ("PmenuThumb", Some("scrollbarSlider.background"), None),
+            // Floating windows
+            (
+                "NormalFloat",
+                Some("editorWidget.background"),
+                Some("editorWidget.foreground"),
+            ),
+            (
+                "FloatBorder",
+                Some("editorWidget.background"),
+                Some("editorWidget.border"),
             ),
+            // Search
+            ("Search", Some("editor.findMatchHighlightBackground"), None),
+            ("IncSearch", Some("editor.findMatchBackground"), None),
+            ("CurSearch", Some("editor.findMatchBackground"), None),
+            // Messages
+            ("ErrorMsg", None, Some("editorError.foreground")),
+            ("WarningMsg", None, Some("editorWarning.foreground")),
+            ("ModeMsg", None, Some("editor.foreground")),
+            ("MoreMsg", None, Some("textLink.foreground")),
+            ("Question", None, Some("textLink.foreground")),
+            ("Title", None, Some("panelTitle.activeForeground")),
+            ("Directory", None, Some("textLink.foreground")),
+            // Diagnostics
+            ("DiagnosticError", None, Some("editorError.foreground")),
+            ("DiagnosticWarn", None, Some("editorWarning.foreground")),
+            ("DiagnosticInfo", None, Some("editorInfo.foreground")),
+            ("DiagnosticHint", None, Some("editorHint.foreground")),
             // Diffs
-            ("DiffAdd", Some("diffEditor.insertedTextBackground"), None),
-            ("DiffDelete", Some("diffEditor.removedTextBackground"), None),
+            ("DiffAdd", Some("diffEditor.insertedLineBackground"), None),
+            ("DiffDelete", Some("diffEditor.removedLineBackground"), None),
+            // VSCode has no colors for changed lines, and shows them on the
+            // modified side of a diff as inserted lines with inserted words
+            (
+                "DiffChange",
+                Some("diffEditor.insertedLineBackground"),
+                None,
+            ),
+            ("DiffText", Some("diffEditor.insertedTextBackground"), None),
+            ("Added", None, Some("gitDecoration.addedResourceForeground")),
+            (
+                "Changed",
+                None,
+                Some("gitDecoration.modifiedResourceForeground"),
+            ),
+            (
+                "Removed",
+                None,
+                Some("gitDecoration.deletedResourceForeground"),
+            ),
+            // Git signs in the gutter
+            ("GitSignsAdd", None, Some("editorGutter.addedBackground")),
+            (
+                "GitSignsChange",
+                None,
+                Some("editorGutter.modifiedBackground"),
+            ),
+            (
+                "GitSignsDelete",
+                None,
+                Some("editorGutter.deletedBackground"),
+            ),
             // Normal and visual modes
             (
                 "Normal",
@@ -119,15 +230,83 @@ pub fn highlights() -> Highlight {
                 Some("editor.foreground"),
             ),
             ("Visual", Some("editor.selectionBackground"), None),
+            (
+                "Cursor",
+                Some("editorCursor.foreground"),
+                Some("editorCursor.background"),
+            ),
+            (
+                "QuickFixLine",
+                Some("list.activeSelectionBackground"),
+                Some("list.activeSelectionForeground"),
+            ),
+            (
+                "SnippetTabstop",
+                Some("editor.snippetTabstopHighlightBackground"),
+                None,
+            ),
+            // Brackets
+            ("MatchParen", Some("editorBracketMatch.background"), None),
+            (
+                "RainbowDelimiterRed",
+                None,
+                Some("editorBracketHighlight.foreground1"),
+            ),
+            (
+                "RainbowDelimiterYellow",
+                None,
+                Some("editorBracketHighlight.foreground2"),
+            ),
+            (
+                "RainbowDelimiterBlue",
+                None,
+                Some("editorBracketHighlight.foreground3"),
+            ),
+            (
+                "RainbowDelimiterOrange",
+                None,
+                Some("editorBracketHighlight.foreground4"),
+            ),
+            (
+                "RainbowDelimiterGreen",
+                None,
+                Some("editorBracketHighlight.foreground5"),
+            ),
+            (
+                "RainbowDelimiterViolet",
+                None,
+                Some("editorBracketHighlight.foreground6"),
+            ),
+            // rainbow-delimiters.nvim has one level more than VSCode. VSCode
+            // cycles through the levels a theme sets, and the seventh one
+            // wraps around to the first color whether it sets three or six
+            (
+                "RainbowDelimiterCyan",
+                None,
+                Some("editorBracketHighlight.foreground1"),
+            ),
             // Misc
-            ("CursorLine", Some("editor.selectionBackground"), None),
+            ("CursorLine", Some("editor.lineHighlightBackground"), None),
+            ("CursorColumn", Some("editor.lineHighlightBackground"), None),
             ("ColorColumn", Some("editor.selectionBackground"), None),
-            ("SignColumn", Some("editor.background"), None),
+            ("SignColumn", Some("editorGutter.background"), None),
+            (
+                "FoldColumn",
+                Some("editorGutter.background"),
+                Some("editorLineNumber.foreground"),
+            ),
             (
                 "LineNr",
                 Some("editorLineNumber.background"),
                 Some("editorLineNumber.foreground"),
             ),
+            (
+                "CursorLineNr",
+                Some("editorLineNumber.background"),
+                Some("editorLineNumber.activeForeground"),
+            ),
+            ("Whitespace", None, Some("editorWhitespace.foreground")),
+            ("SpecialKey", None, Some("editorWhitespace.foreground")),
             // Tabs
             (
                 "TabLine",
@@ -136,8 +315,8 @@ pub fn highlights() -> Highlight {
             ),
             (
                 "TabLineSel",
-                Some("tab.activeForeground"),
                 Some("tab.activeBackground"),
+                Some("tab.activeForeground"),
             ),
             (
                 "TabLineFill",
@@ -147,13 +326,40 @@ pub fn highlights() -> Highlight {
             // Treesitter
             ("TSPunctDelimiter", None, Some("editor.foreground")),
         ],
+        undercurls: vec![
+            ("SpellBad", "editorError.foreground"),
+            ("SpellCap", "editorWarning.foreground"),
+            ("SpellLocal", "editorInfo.foreground"),
+            ("SpellRare", "editorHint.foreground"),
+            ("DiagnosticUnderlineError", "editorError.foreground"),
+            ("DiagnosticUnderlineWarn", "editorWarning.foreground"),
+            ("DiagnosticUnderlineInfo", "editorInfo.foreground"),
+            ("DiagnosticUnderlineHint", "editorHint.foreground"),
+        ],
         links: vec![
             // Vim builtins
             ("Folded", "Comment"),
-            ("Whitespace", "Comment"),
             ("NonText", "Comment"),
-            ("CursorLineNr", "Identifier"),
-
+            ("EndOfBuffer", "NonText"),
+            ("Conceal", "Comment"),
+            ("lCursor", "Cursor"),
+            ("CursorIM", "Cursor"),
+            ("TermCursor", "Cursor"),
+            ("Substitute", "IncSearch"),
+            ("VisualNOS", "Visual"),
+            ("LineNrAbove", "LineNr"),
+            ("LineNrBelow", "LineNr"),
+            ("CursorLineFold", "FoldColumn"),
+            ("CursorLineSign", "SignColumn"),
+            ("FloatTitle", "Title"),
+            ("FloatFooter", "FloatTitle"),
+            ("VertSplit", "WinSeparator"),
+            ("PmenuKind", "Pmenu"),
+            ("PmenuKindSel", "PmenuSel"),
+            ("PmenuExtra", "Pmenu"),
+            ("PmenuExtraSel", "PmenuSel"),
+            ("StatusLineTerm", "StatusLine"),
+            ("StatusLineTermNC", "StatusLineNC"),
             // Treesitter
             ("TSFuncMacro", "Macro"),
             ("TSFunction", "Function"),
@@ -169,42 +375,210 @@ pub fn highlights() -> Highlight {
             ("TSString", "String"),
             ("TSConditional", "Conditional"),
             ("TSConstant", "Constant"),
-            ("TSTag", "MyTag"),
-            ("TSPunctBracket", "MyTag"),
+            ("TSTag", "Tag"),
+            ("TSPunctBracket", "Delimiter"),
             ("TSPunctSpecial", "TSPunctDelimiter"),
             ("TSTagDelimiter", "Type"),
             ("TSKeyword", "Keyword"),
             ("TSRepeat", "Repeat"),
             ("TSConstBuiltin", "TSVariableBuiltin"),
-
+            // Diffs
+            ("diffAdded", "Added"),
+            ("diffChanged", "Changed"),
+            ("diffRemoved", "Removed"),
+            ("@diff.plus", "Added"),
+            ("@diff.delta", "Changed"),
+            ("@diff.minus", "Removed"),
+            // Git signs
+            ("GitGutterAdd", "GitSignsAdd"),
+            ("GitGutterChange", "GitSignsChange"),
+            ("GitGutterDelete", "GitSignsDelete"),
+            ("SignifySignAdd", "GitSignsAdd"),
+            ("SignifySignChange", "GitSignsChange"),
+            ("SignifySignDelete", "GitSignsDelete"),
             // Telescope
-            ("TelescopeNormal", "Normal")
+            ("TelescopeNormal", "Normal"),
         ],
     }
 }
 
-#[derive(Debug)]
+#[derive(Serialize, Debug, Clone)]
 pub struct VimHighlight {
     pub group: String,
     pub background: Option<String>,
     pub foreground: Option<String>,
+    /// Color of underlines and undercurls
+    pub special: Option<String>,
     pub text_style: Option<String>,
+    /// The parts of the theme the highlight was resolved from
+    pub sources: Vec<Source>,
+}
+
+impl VimHighlight {
+    /// Whether the highlight sets any color or attribute
+    pub fn has_attributes(&self) -> bool {
+        self.background.is_some()
+            || self.foreground.is_some()
+            || self.special.is_some()
+            || self.text_style.is_some()
+    }
+}
+
+/// A part of a VSCode theme that contributed to a highlight
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "kind", content = "name", rename_all = "lowercase")]
+pub enum Source {
+    /// A `tokenColors` scope
+    Scope(String),
+    /// A `colors` key
+    Color(String),
+    /// A `semanticTokenColors` selector
+    Semantic(String),
 }
 
+/// Maps a VSCode `fontStyle`, e.g. "italic underline", to Vim attributes
 pub fn map_font_styles(style: &Option<String>) -> Option<String> {
-    if let Some(style) = style {
-        let style = style.as_str();
-        let result = match style {
-            "italic" => Some("italic".to_string()),
-            "bold" => Some("bold".to_string()),
-            _ => None,
-        };
-        if result.is_some() {
-            result
-        } else {
-            None
-        }
-    } else {
+    let attributes: Vec<&str> = style
+        .as_deref()
+        .unwrap_or_default()
+        .split_whitespace()
+        .filter(|style| ["italic", "bold", "underline", "strikethrough"].contains(style))
+        .collect();
+
+    if attributes.is_empty() {
         None
+    } else {
+        Some(attributes.join(","))
+    }
+}
+
+/// The Neovim group LSP semantic tokens matching a `semanticTokenColors`
+/// selector are highlighted with, if there is one. Selectors such as
+/// `*.declaration` map to `@lsp.mod.declaration`, `function` to
+/// `@lsp.type.function` and `variable.readonly:rust` to
+/// `@lsp.typemod.variable.readonly.rust`.
+pub fn semantic_group(selector: &str) -> Option<String> {
+    let (selector, language) = match selector.split_once(':') {
+        Some((selector, language)) => (selector, Some(language)),
+        None => (selector, None),
+    };
+    let mut group = match selector.split('.').collect::<Vec<&str>>().as_slice() {
+        ["*", modifier] => format!("@lsp.mod.{}", modifier),
+        [kind] if *kind != "*" => format!("@lsp.type.{}", kind),
+        [kind, modifier] => format!("@lsp.typemod.{}.{}", kind, modifier),
+        // Neovim has no groups for several modifiers at once
+        _ => return None,
+    };
+    if let Some(language) = language {
+        group = format!("{}.{}", group, language);
     }
+    Some(group).filter(|group| is_group_name(group))
+}
+
+/// Whether `name` is a valid highlight group name, made of letters, digits,
+/// `_`, `.`, `-` and `@`
+pub fn is_group_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "_.-@".contains(c))
 }
+
+/// The built-in UI groups of `:h highlight-groups`, which any colorscheme is
+/// expected to set
+pub const BUILTIN_GROUPS: &[&str] = &[
+    "ColorColumn",
+    "Conceal",
+    "CurSearch",
+    "Cursor",
+    "lCursor",
+    "CursorIM",
+    "CursorColumn",
+    "CursorLine",
+    "Directory",
+    "DiffAdd",
+    "DiffChange",
+    "DiffDelete",
+    "DiffText",
+    "EndOfBuffer",
+    "TermCursor",
+    "ErrorMsg",
+    "WinSeparator",
+    "Folded",
+    "FoldColumn",
+    "SignColumn",
+    "IncSearch",
+    "Substitute",
+    "LineNr",
+    "LineNrAbove",
+    "LineNrBelow",
+    "CursorLineNr",
+    "CursorLineFold",
+    "CursorLineSign",
+    "MatchParen",
+    "ModeMsg",
+    "MsgArea",
+    "MsgSeparator",
+    "MoreMsg",
+    "NonText",
+    "Normal",
+    "NormalFloat",
+    "FloatBorder",
+    "FloatTitle",
+    "FloatFooter",
+    "NormalNC",
+    "Pmenu",
+    "PmenuSel",
+    "PmenuKind",
+    "PmenuKindSel",
+    "PmenuExtra",
+    "PmenuExtraSel",
+    "PmenuSbar",
+    "PmenuThumb",
+    "PmenuMatch",
+    "PmenuMatchSel",
+    "Question",
+    "QuickFixLine",
+    "Search",
+    "SnippetTabstop",
+    "SpecialKey",
+    "SpellBad",
+    "SpellCap",
+    "SpellLocal",
+    "SpellRare",
+    "StatusLine",
+    "StatusLineNC",
+    "StatusLineTerm",
+    "StatusLineTermNC",
+    "TabLine",
+    "TabLineFill",
+    "TabLineSel",
+    "Title",
+    "Visual",
+    "VisualNOS",
+    "WarningMsg",
+    "Whitespace",
+    "WildMenu",
+    "WinBar",
+    "WinBarNC",
+];
+
+/// The groups Vim draws reversed by default, whose style is cleared so that
+/// `:highlight` doesn't swap the colors they are given
+pub const REVERSED_GROUPS: &[&str] = &["StatusLine", "StatusLineNC", "TabLineFill"];
+
+/// The groups whose background `--transparent` clears, so a terminal or
+/// window background shows through
+pub const TRANSPARENT_GROUPS: &[&str] = &[
+    "Normal",
+    "NormalNC",
+    "NormalFloat",
+    "FloatBorder",
+    "SignColumn",
+    "FoldColumn",
+    "LineNr",
+    "CursorLineNr",
+    "EndOfBuffer",
+    "WinBar",
+    "WinBarNC",
+];
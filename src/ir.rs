@@ -0,0 +1,255 @@
+use std::{collections::HashMap, fmt};
+
+use serde::Serialize;
+
+use crate::{colors::ColorError, highlights::VimHighlight};
+
+/// Palette value of fully transparent colors, which are emitted as `NONE` so
+/// the attribute is left unset
+pub const NONE: &str = "NONE";
+
+/// A fully resolved theme, independent of the output language.
+///
+/// Produced by `generators::resolve` and consumed by the `ConfigGenerator`
+/// backends, so every backend sees the whole theme at once.
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct ResolvedTheme {
+    pub metadata: Metadata,
+    pub palette: Vec<PaletteColor>,
+    pub highlights: Vec<VimHighlight>,
+    pub links: Vec<VimLink>,
+    /// Colors of the theme that couldn't be parsed, and were left out
+    pub invalid_colors: Vec<InvalidColor>,
+}
+
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct Metadata {
+    /// The `name` field of the VSCode theme, if any
+    pub name: Option<String>,
+    /// The `type` field of the VSCode theme (`dark`, `light`, `hc`...)
+    pub kind: Option<String>,
+    /// Color every RGBA color of the theme was blended against
+    pub background: String,
+    /// Kind of the theme this one was synthesized from by
+    /// `synthesize::invert_variant`, if it was
+    pub synthesized_from: Option<String>,
+}
+
+impl Metadata {
+    /// Describes how the theme was synthesized, for the generated headers
+    pub fn synthesized_note(&self) -> Option<String> {
+        let from = self.synthesized_from.as_ref()?;
+        let to = self.kind.as_deref().unwrap_or("inverted");
+        Some(format!(
+            "This is a {} variant synthesized from a {} theme by inverting its lightness",
+            to, from
+        ))
+    }
+}
+
+/// A named color, referenced by the `VimHighlight`s of the theme
+#[derive(Serialize, Debug, Clone)]
+pub struct PaletteColor {
+    pub name: String,
+    pub color: String,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct VimLink {
+    pub group: String,
+    pub target: String,
+}
+
+/// A color of the theme that couldn't be parsed
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct InvalidColor {
+    /// Where the color is in the theme, e.g. `colors["editor.foreground"]`
+    pub location: String,
+    pub error: ColorError,
+}
+
+impl fmt::Display for InvalidColor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.location, self.error)
+    }
+}
+
+impl ResolvedTheme {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("The resolved theme is always serializable")
+    }
+
+    /// Returns the highlight that ends up applied to `group`. As links are
+    /// emitted with `highlight!`, they take precedence over the group's own
+    /// colors, and later definitions of a group override earlier ones.
+    pub fn highlight(&self, group: &str) -> Option<&VimHighlight> {
+        let mut group = group;
+        // Bounded, since a link cycle would otherwise loop forever
+        for _ in 0..16 {
+            match self.links.iter().find(|link| link.group == group) {
+                Some(link) => group = &link.target,
+                None => return self.highlights.iter().rev().find(|h| h.group == group),
+            }
+        }
+        None
+    }
+
+    /// Returns the (foreground, background) hex colors of `Normal`, falling
+    /// back to the editor background and black or white text
+    pub fn normal_colors(&self) -> (String, String) {
+        let normal = self.highlight("Normal");
+        let color = |name: Option<&String>| name.and_then(|name| self.color(name));
+
+        let background = color(normal.and_then(|n| n.background.as_ref()))
+            .unwrap_or(&self.metadata.background)
+            .to_string();
+        let foreground = color(normal.and_then(|n| n.foreground.as_ref()))
+            .map(str::to_string)
+            .unwrap_or_else(|| {
+                if self.metadata.kind.as_deref() == Some("light") {
+                    "#000000".to_string()
+                } else {
+                    "#ffffff".to_string()
+                }
+            });
+
+        (foreground, background)
+    }
+
+    /// Returns the hex color of a palette entry, or `None` when it is `NONE`
+    pub fn color(&self, name: &str) -> Option<&str> {
+        self.palette_value(name).filter(|color| *color != NONE)
+    }
+
+    fn palette_value(&self, name: &str) -> Option<&str> {
+        self.palette
+            .iter()
+            .find(|color| color.name == name)
+            .map(|color| color.color.as_str())
+    }
+
+    /// Clears the background of `groups`, so the terminal or window behind
+    /// them shows through. Linked groups are left alone.
+    pub fn clear_backgrounds(&mut self, groups: &[&str]) {
+        let none = match self.palette.iter().find(|color| color.color == NONE) {
+            Some(color) => color.name.clone(),
+            None => {
+                let name = format!("Color{}", self.palette.len());
+                self.palette.push(PaletteColor {
+                    name: name.clone(),
+                    color: NONE.to_string(),
+                });
+                name
+            }
+        };
+
+        for group in groups {
+            if self.links.iter().any(|link| link.group == *group) {
+                continue;
+            }
+            let mut found = false;
+            for highlight in self.highlights.iter_mut().filter(|h| h.group == *group) {
+                highlight.background = Some(none.clone());
+                found = true;
+            }
+            if !found {
+                self.highlights.push(VimHighlight {
+                    group: group.to_string(),
+                    background: Some(none.clone()),
+                    foreground: None,
+                    special: None,
+                    text_style: None,
+                    sources: Vec::new(),
+                });
+            }
+        }
+    }
+}
+
+/// A highlight group, and which of its colors ("fg", "bg" or "sp")
+type Role = (String, &'static str);
+/// The colors of a role in the (dark, light) variants
+type ColorPair = (Option<String>, Option<String>);
+
+/// Renames the palettes of the dark and light variants of a theme, so every
+/// color is referred to by the same name in both. A name takes the color the
+/// other variant uses when a variant doesn't color that group.
+pub fn share_palette(dark: &mut ResolvedTheme, light: &mut ResolvedTheme) {
+    let mut roles: Vec<(Role, ColorPair)> = Vec::new();
+    for (theme, is_dark) in [(&*dark, true), (&*light, false)] {
+        for highlight in &theme.highlights {
+            let colors = [
+                (&highlight.foreground, "fg"),
+                (&highlight.background, "bg"),
+                (&highlight.special, "sp"),
+            ];
+            for (name, attribute) in colors {
+                let color = match name.as_ref().and_then(|name| theme.palette_value(name)) {
+                    Some(color) => color.to_string(),
+                    None => continue,
+                };
+                let role = (highlight.group.clone(), attribute);
+                let idx = match roles.iter().position(|(r, _)| *r == role) {
+                    Some(idx) => idx,
+                    None => {
+                        roles.push((role, (None, None)));
+                        roles.len() - 1
+                    }
+                };
+                if is_dark {
+                    roles[idx].1 .0 = Some(color);
+                } else {
+                    roles[idx].1 .1 = Some(color);
+                }
+            }
+        }
+    }
+
+    // Every distinct (dark, light) pair of colors gets a name
+    let mut pairs: Vec<ColorPair> = Vec::new();
+    let mut names: HashMap<Role, String> = HashMap::new();
+    for (role, pair) in roles {
+        let idx = match pairs.iter().position(|p| *p == pair) {
+            Some(idx) => idx,
+            None => {
+                pairs.push(pair);
+                pairs.len() - 1
+            }
+        };
+        names.insert(role, format!("Color{}", idx));
+    }
+
+    let palette = |is_dark: bool| {
+        pairs
+            .iter()
+            .enumerate()
+            .map(|(idx, (dark, light))| {
+                let (own, other) = if is_dark {
+                    (dark, light)
+                } else {
+                    (light, dark)
+                };
+                PaletteColor {
+                    name: format!("Color{}", idx),
+                    color: own.clone().or_else(|| other.clone()).unwrap_or_default(),
+                }
+            })
+            .collect::<Vec<_>>()
+    };
+
+    for (theme, is_dark) in [(dark, true), (light, false)] {
+        for highlight in &mut theme.highlights {
+            let group = highlight.group.clone();
+            if highlight.foreground.is_some() {
+                highlight.foreground = names.get(&(group.clone(), "fg")).cloned();
+            }
+            if highlight.background.is_some() {
+                highlight.background = names.get(&(group.clone(), "bg")).cloned();
+            }
+            if highlight.special.is_some() {
+                highlight.special = names.get(&(group, "sp")).cloned();
+            }
+        }
+        theme.palette = palette(is_dark);
+    }
+}
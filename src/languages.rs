@@ -0,0 +1,530 @@
+use crate::decoder::{VSCodeHighlight, VSCodeScope, VSCodeScopeSettings};
+
+/// A tuple containing (language-qualified TextMate scopes, Vim target
+/// highlight). A token can carry several space separated scopes, such as the
+/// generic and per-level scopes of a heading.
+type LanguageToken = (&'static str, &'static str);
+
+/// The legacy syntax groups and treesitter captures of a language
+#[derive(Debug)]
+pub struct Language {
+    pub name: &'static str,
+    pub tokens: Vec<LanguageToken>,
+}
+
+pub fn languages() -> Vec<Language> {
+    vec![
+        Language {
+            name: "HTML",
+            tokens: vec![
+                ("entity.name.tag.html", "htmlTagName"),
+                ("entity.name.tag.html", "@tag.html"),
+                ("punctuation.definition.tag.begin.html", "htmlTag"),
+                ("punctuation.definition.tag.end.html", "htmlEndTag"),
+                (
+                    "punctuation.definition.tag.begin.html",
+                    "@tag.delimiter.html",
+                ),
+                ("entity.other.attribute-name.html", "htmlArg"),
+                ("entity.other.attribute-name.html", "@tag.attribute.html"),
+                ("string.quoted.double.html", "htmlString"),
+                ("constant.character.entity.html", "htmlSpecialChar"),
+                ("constant.character.entity.html", "@character.special.html"),
+                ("markup.heading.1 heading.1.markdown", "htmlH1"),
+                ("markup.heading.2 heading.2.markdown", "htmlH2"),
+                ("markup.heading.3 heading.3.markdown", "htmlH3"),
+                ("markup.heading.4 heading.4.markdown", "htmlH4"),
+                ("markup.heading.5 heading.5.markdown", "htmlH5"),
+                ("markup.heading.6 heading.6.markdown", "htmlH6"),
+                ("comment.block.html", "htmlComment"),
+            ],
+        },
+        Language {
+            name: "CSS",
+            tokens: vec![
+                ("entity.name.tag.css", "cssTagName"),
+                ("entity.name.tag.css", "@tag.css"),
+                ("entity.other.attribute-name.class.css", "cssClassName"),
+                ("entity.other.attribute-name.id.css", "cssIdentifier"),
+                (
+                    "entity.other.attribute-name.pseudo-class.css",
+                    "cssPseudoClassId",
+                ),
+                ("support.type.property-name.css", "cssProp"),
+                ("support.type.property-name.css", "@property.css"),
+                ("support.constant.property-value.css", "cssAttr"),
+                ("constant.other.color.rgb-value.hex.css", "cssColor"),
+                ("constant.numeric.css", "cssValueNumber"),
+                ("constant.numeric.css", "@number.css"),
+                ("keyword.other.unit.css", "cssUnitDecorators"),
+                ("support.function.misc.css", "cssFunctionName"),
+                ("support.function.misc.css", "@function.css"),
+                ("keyword.other.important.css", "cssImportant"),
+                ("keyword.control.at-rule.css", "cssAtRule"),
+            ],
+        },
+        Language {
+            name: "Markdown",
+            tokens: vec![
+                (
+                    "markup.heading.markdown markup.heading.1 heading.1.markdown",
+                    "markdownH1",
+                ),
+                (
+                    "markup.heading.markdown markup.heading.1 heading.1.markdown",
+                    "@markup.heading.1",
+                ),
+                (
+                    "markup.heading.markdown markup.heading.1 heading.1.markdown",
+                    "@markup.heading.1.markdown",
+                ),
+                (
+                    "markup.heading.markdown markup.heading.2 heading.2.markdown",
+                    "markdownH2",
+                ),
+                (
+                    "markup.heading.markdown markup.heading.2 heading.2.markdown",
+                    "@markup.heading.2",
+                ),
+                (
+                    "markup.heading.markdown markup.heading.2 heading.2.markdown",
+                    "@markup.heading.2.markdown",
+                ),
+                (
+                    "markup.heading.markdown markup.heading.3 heading.3.markdown",
+                    "markdownH3",
+                ),
+                (
+                    "markup.heading.markdown markup.heading.3 heading.3.markdown",
+                    "@markup.heading.3",
+                ),
+                (
+                    "markup.heading.markdown markup.heading.3 heading.3.markdown",
+                    "@markup.heading.3.markdown",
+                ),
+                (
+                    "markup.heading.markdown markup.heading.4 heading.4.markdown",
+                    "markdownH4",
+                ),
+                (
+                    "markup.heading.markdown markup.heading.4 heading.4.markdown",
+                    "@markup.heading.4",
+                ),
+                (
+                    "markup.heading.markdown markup.heading.4 heading.4.markdown",
+                    "@markup.heading.4.markdown",
+                ),
+                (
+                    "markup.heading.markdown markup.heading.5 heading.5.markdown",
+                    "markdownH5",
+                ),
+                (
+                    "markup.heading.markdown markup.heading.5 heading.5.markdown",
+                    "@markup.heading.5",
+                ),
+                (
+                    "markup.heading.markdown markup.heading.5 heading.5.markdown",
+                    "@markup.heading.5.markdown",
+                ),
+                (
+                    "markup.heading.markdown markup.heading.6 heading.6.markdown",
+                    "markdownH6",
+                ),
+                (
+                    "markup.heading.markdown markup.heading.6 heading.6.markdown",
+                    "@markup.heading.6",
+                ),
+                (
+                    "markup.heading.markdown markup.heading.6 heading.6.markdown",
+                    "@markup.heading.6.markdown",
+                ),
+                (
+                    "punctuation.definition.heading.markdown",
+                    "markdownHeadingDelimiter",
+                ),
+                ("markup.bold.markdown", "markdownBold"),
+                ("markup.bold.markdown", "@markup.strong.markdown_inline"),
+                ("markup.italic.markdown", "markdownItalic"),
+                ("markup.italic.markdown", "@markup.italic.markdown_inline"),
+                ("markup.inline.raw.string.markdown", "markdownCode"),
+                (
+                    "markup.inline.raw.string.markdown",
+                    "@markup.raw.markdown_inline",
+                ),
+                ("markup.fenced_code.block.markdown", "markdownCodeBlock"),
+                (
+                    "markup.fenced_code.block.markdown",
+                    "@markup.raw.block.markdown",
+                ),
+                ("markup.underline.link.markdown", "markdownUrl"),
+                (
+                    "markup.underline.link.markdown",
+                    "@markup.link.url.markdown_inline",
+                ),
+                ("string.other.link.title.markdown", "markdownLinkText"),
+                (
+                    "string.other.link.title.markdown",
+                    "@markup.link.label.markdown_inline",
+                ),
+                ("markup.quote.markdown", "markdownBlockquote"),
+                ("markup.quote.markdown", "@markup.quote.markdown"),
+                (
+                    "punctuation.definition.list.begin.markdown",
+                    "markdownListMarker",
+                ),
+                (
+                    "punctuation.definition.list.begin.markdown",
+                    "@markup.list.markdown",
+                ),
+            ],
+        },
+        Language {
+            name: "JSON",
+            tokens: vec![
+                ("support.type.property-name.json", "jsonKeyword"),
+                ("support.type.property-name.json", "@property.json"),
+                ("string.quoted.double.json", "jsonString"),
+                ("string.quoted.double.json", "@string.json"),
+                ("constant.numeric.json", "jsonNumber"),
+                ("constant.numeric.json", "@number.json"),
+                ("constant.language.json", "jsonBoolean"),
+                ("constant.language.json", "jsonNull"),
+                ("constant.language.json", "@boolean.json"),
+                ("constant.language.json", "@constant.builtin.json"),
+                ("punctuation.definition.dictionary.begin.json", "jsonBraces"),
+                (
+                    "punctuation.definition.dictionary.begin.json",
+                    "@punctuation.bracket.json",
+                ),
+                (
+                    "punctuation.separator.dictionary.key-value.json",
+                    "jsonNoise",
+                ),
+                (
+                    "punctuation.separator.dictionary.key-value.json",
+                    "@punctuation.delimiter.json",
+                ),
+            ],
+        },
+        Language {
+            name: "YAML",
+            tokens: vec![
+                ("entity.name.tag.yaml", "yamlBlockMappingKey"),
+                ("entity.name.tag.yaml", "@property.yaml"),
+                (
+                    "punctuation.separator.key-value.mapping.yaml",
+                    "yamlKeyValueDelimiter",
+                ),
+                (
+                    "punctuation.separator.key-value.mapping.yaml",
+                    "@punctuation.delimiter.yaml",
+                ),
+                ("string.unquoted.plain.out.yaml", "yamlPlainScalar"),
+                ("string.unquoted.plain.out.yaml", "@string.yaml"),
+                ("constant.language.boolean.yaml", "yamlBool"),
+                ("constant.language.boolean.yaml", "@boolean.yaml"),
+                ("constant.numeric.integer.yaml", "yamlInteger"),
+                ("constant.numeric.float.yaml", "yamlFloat"),
+                ("constant.numeric.integer.yaml", "@number.yaml"),
+                ("entity.other.document.begin.yaml", "yamlDocumentStart"),
+                ("variable.other.alias.yaml", "yamlAlias"),
+                ("entity.name.type.anchor.yaml", "yamlAnchor"),
+            ],
+        },
+        Language {
+            name: "Rust",
+            tokens: vec![
+                ("entity.name.function.macro.rust", "rustMacro"),
+                ("entity.name.function.macro.rust", "@function.macro.rust"),
+                ("entity.name.function.rust", "rustFuncName"),
+                ("entity.name.function.rust", "@function.rust"),
+                ("entity.name.type.lifetime.rust", "rustLifetime"),
+                ("entity.name.type.rust", "rustType"),
+                ("entity.name.type.rust", "@type.rust"),
+                ("entity.name.namespace.rust", "rustModPath"),
+                ("entity.name.namespace.rust", "@module.rust"),
+                ("meta.attribute.rust", "rustAttribute"),
+                ("meta.attribute.rust", "@attribute.rust"),
+                ("variable.language.self.rust", "rustSelf"),
+                ("variable.language.self.rust", "@variable.builtin.rust"),
+                ("storage.modifier.rust", "rustStorage"),
+                ("storage.modifier.rust", "@keyword.modifier.rust"),
+            ],
+        },
+        Language {
+            name: "Python",
+            tokens: vec![
+                ("entity.name.function.python", "pythonFunction"),
+                ("entity.name.function.python", "@function.python"),
+                (
+                    "entity.name.function.decorator.python",
+                    "pythonDecoratorName",
+                ),
+                ("entity.name.function.decorator.python", "@attribute.python"),
+                ("support.function.builtin.python", "pythonBuiltin"),
+                (
+                    "support.function.builtin.python",
+                    "@function.builtin.python",
+                ),
+                ("keyword.control.import.python", "pythonInclude"),
+                ("keyword.control.import.python", "@keyword.import.python"),
+                ("storage.type.function.python", "pythonStatement"),
+                ("storage.type.function.python", "@keyword.function.python"),
+                ("constant.character.escape.python", "pythonEscape"),
+                ("constant.character.escape.python", "@string.escape.python"),
+                ("support.type.exception.python", "pythonExceptions"),
+                (
+                    "variable.parameter.function.language.special.self.python",
+                    "@variable.builtin.python",
+                ),
+            ],
+        },
+        Language {
+            name: "JavaScript",
+            tokens: vec![
+                ("storage.type.function.js", "javaScriptFunction"),
+                ("storage.type.function.js", "@keyword.function.javascript"),
+                ("entity.name.function.js", "jsFuncName"),
+                ("entity.name.function.js", "@function.javascript"),
+                ("variable.language.this.js", "jsThis"),
+                ("variable.language.this.js", "@variable.builtin.javascript"),
+                ("entity.name.type.class.js", "jsClassDefinition"),
+                ("entity.name.type.class.js", "@type.javascript"),
+                ("meta.object-literal.key.js", "jsObjectKey"),
+                ("meta.object-literal.key.js", "@property.javascript"),
+                ("support.class.builtin.js", "javaScriptGlobal"),
+                (
+                    "punctuation.definition.template-expression.begin.js",
+                    "jsTemplateBraces",
+                ),
+            ],
+        },
+        Language {
+            name: "TypeScript",
+            tokens: vec![
+                ("keyword.control.import.ts", "typescriptImport"),
+                ("keyword.control.import.ts", "@keyword.import.typescript"),
+                ("keyword.control.export.ts", "typescriptExport"),
+                ("entity.name.function.ts", "typescriptFuncName"),
+                ("entity.name.function.ts", "@function.typescript"),
+                ("entity.name.type.ts", "typescriptTypeReference"),
+                ("entity.name.type.ts", "@type.typescript"),
+                ("entity.name.type.interface.ts", "typescriptInterfaceName"),
+                ("entity.name.type.class.ts", "typescriptClassName"),
+                ("meta.decorator.ts", "typescriptDecorator"),
+                ("meta.decorator.ts", "@attribute.typescript"),
+                ("variable.other.property.ts", "typescriptMember"),
+                ("variable.other.property.ts", "@property.typescript"),
+                ("storage.type.ts", "typescriptVariable"),
+            ],
+        },
+        Language {
+            name: "Go",
+            tokens: vec![
+                ("keyword.package.go", "goPackage"),
+                ("keyword.import.go", "goImport"),
+                ("keyword.import.go", "@keyword.import.go"),
+                ("keyword.function.go", "goDeclaration"),
+                ("keyword.function.go", "@keyword.function.go"),
+                ("entity.name.function.go", "goFunction"),
+                ("entity.name.function.go", "@function.go"),
+                ("entity.name.type.go", "goTypeName"),
+                ("entity.name.type.go", "@type.go"),
+                ("entity.name.package.go", "@module.go"),
+                ("storage.type.numeric.go", "goSignedInts"),
+                ("storage.type.numeric.go", "goUnsignedInts"),
+                ("storage.type.numeric.go", "goFloats"),
+                ("storage.type.numeric.go", "@type.builtin.go"),
+                ("storage.type.string.go", "goType"),
+                ("support.function.builtin.go", "goBuiltins"),
+                ("support.function.builtin.go", "@function.builtin.go"),
+            ],
+        },
+        Language {
+            name: "Lua",
+            tokens: vec![
+                ("entity.name.function.lua", "@function.lua"),
+                ("support.function.lua", "luaFunc"),
+                ("support.function.lua", "@function.builtin.lua"),
+                ("keyword.local.lua", "luaStatement"),
+                ("keyword.local.lua", "@keyword.lua"),
+                ("constant.language.nil.lua", "luaConstant"),
+                ("constant.language.nil.lua", "@constant.builtin.lua"),
+                ("keyword.operator.lua", "luaOperator"),
+                ("keyword.operator.lua", "@operator.lua"),
+                ("comment.line.double-dash.lua", "luaComment"),
+                ("comment.line.double-dash.lua", "@comment.lua"),
+                ("string.quoted.double.lua", "luaString"),
+                ("string.quoted.double.lua", "@string.lua"),
+            ],
+        },
+    ]
+}
+
+/// Whether the TextMate `selector` of a theme applies to `scope`, i.e. whether
+/// it is `scope` or one of its dot separated prefixes
+pub fn selector_matches(selector: &str, scope: &str) -> bool {
+    match scope.strip_prefix(selector) {
+        Some(rest) => !selector.is_empty() && (rest.is_empty() || rest.starts_with('.')),
+        None => false,
+    }
+}
+
+/// Returns the settings the rules of a theme give to a token with the space
+/// separated scopes `scope`, along with the selectors they were taken from.
+/// Like in TextMate, each setting comes from the most specific selector
+/// setting it, and later rules win ties. Descendant selectors are skipped.
+pub fn settings_of<'a>(
+    tokens: &'a [VSCodeHighlight],
+    scope: &str,
+) -> Option<(VSCodeScopeSettings, Vec<&'a str>)> {
+    let mut matches: Vec<(&'a str, &'a VSCodeScopeSettings)> = Vec::new();
+    for token in tokens {
+        let selectors = match &token.scope {
+            Some(VSCodeScope::Single(selector)) => std::slice::from_ref(selector),
+            Some(VSCodeScope::Multiple(selectors)) => selectors.as_slice(),
+            None => &[],
+        };
+        // A rule can list several comma separated selectors in one string.
+        // Descendant selectors such as `source.rust string` depend on the
+        // scopes around the token, which aren't known here, so they are skipped
+        let selectors = selectors
+            .iter()
+            .flat_map(|selectors| selectors.split(','))
+            .map(str::trim)
+            .filter(|selector| !selector.contains(char::is_whitespace));
+        for selector in selectors {
+            if scope
+                .split_whitespace()
+                .any(|scope| selector_matches(selector, scope))
+            {
+                matches.push((selector, &token.settings));
+            }
+        }
+    }
+    if matches.is_empty() {
+        return None;
+    }
+    // Stable, so later rules stay after earlier ones of the same specificity
+    matches.sort_by_key(|(selector, _)| selector.split('.').count());
+
+    let mut settings = VSCodeScopeSettings {
+        foreground: None,
+        background: None,
+        font_style: None,
+    };
+    let mut sources = [None; 3];
+    for (selector, matched) in matches {
+        if matched.foreground.is_some() {
+            settings.foreground = matched.foreground.clone();
+            sources[0] = Some(selector);
+        }
+        if matched.background.is_some() {
+            settings.background = matched.background.clone();
+            sources[1] = Some(selector);
+        }
+        if matched.font_style.is_some() {
+            settings.font_style = matched.font_style.clone();
+            sources[2] = Some(selector);
+        }
+    }
+
+    let mut selectors: Vec<&str> = Vec::new();
+    for selector in sources.iter().flatten() {
+        if !selectors.contains(selector) {
+            selectors.push(selector);
+        }
+    }
+    Some((settings, selectors))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decoder;
+
+    #[test]
+    fn can_match_the_most_specific_selector() {
+        assert!(selector_matches("entity.name.tag", "entity.name.tag.html"));
+        assert!(selector_matches(
+            "entity.name.tag.html",
+            "entity.name.tag.html"
+        ));
+        assert!(!selector_matches("entity.name.ta", "entity.name.tag.html"));
+        assert!(!selector_matches(
+            "entity.name.tag.css",
+            "entity.name.tag.html"
+        ));
+
+        let theme = decoder::parse_reader(
+            r##"{ "tokenColors": [
+                { "scope": "entity.name.tag.html", "settings": { "fontStyle": "italic" } },
+                { "scope": "entity.name.tag", "settings": { "foreground": "#f38ba8" } },
+                { "scope": ["entity", "entity.name.tag"], "settings": { "foreground": "#89b4fa" } }
+            ] }"##
+                .as_bytes(),
+        )
+        .unwrap();
+        let (settings, selectors) = settings_of(&theme.tokens, "entity.name.tag.html").unwrap();
+
+        assert_eq!(settings.foreground.as_deref(), Some("#89b4fa"));
+        assert_eq!(settings.font_style.as_deref(), Some("italic"));
+        assert_eq!(selectors, vec!["entity.name.tag", "entity.name.tag.html"]);
+        assert!(settings_of(&theme.tokens, "string.quoted.double.json").is_none());
+    }
+
+    #[test]
+    fn can_split_selector_lists() {
+        let theme = decoder::parse_reader(
+            r##"{ "tokenColors": [
+                { "scope": "comment, entity.name.tag", "settings": { "foreground": "#f38ba8" } },
+                { "scope": "text.html entity.name.tag", "settings": { "foreground": "#89b4fa" } }
+            ] }"##
+                .as_bytes(),
+        )
+        .unwrap();
+        let (settings, selectors) = settings_of(&theme.tokens, "entity.name.tag.html").unwrap();
+
+        assert_eq!(settings.foreground.as_deref(), Some("#f38ba8"));
+        assert_eq!(selectors, vec!["entity.name.tag"]);
+    }
+
+    #[test]
+    fn can_color_heading_levels() {
+        let theme = decoder::parse_reader(
+            r##"{ "tokenColors": [
+                { "scope": "markup.heading", "settings": { "foreground": "#f38ba8", "fontStyle": "bold" } },
+                { "scope": "heading.1.markdown", "settings": { "foreground": "#89b4fa" } },
+                { "scope": "markup.heading.2", "settings": { "foreground": "#a6e3a1" } }
+            ] }"##
+                .as_bytes(),
+        )
+        .unwrap();
+        let resolved = crate::generators::resolve(&theme);
+        let foreground = |group: &str| {
+            let highlight = resolved.highlight(group).unwrap();
+            let name = highlight.foreground.as_ref().unwrap();
+            let color = resolved.palette.iter().find(|color| &color.name == name);
+            (color.unwrap().color.clone(), highlight.text_style.clone())
+        };
+
+        let bold = Some("bold".to_string());
+        assert_eq!(
+            foreground("markdownH1"),
+            ("#89b4fa".to_string(), bold.clone())
+        );
+        assert_eq!(
+            foreground("@markup.heading.2"),
+            ("#a6e3a1".to_string(), bold.clone())
+        );
+        assert_eq!(foreground("htmlH3"), ("#f38ba8".to_string(), bold));
+
+        // Groups several languages share are merged rather than repeated
+        for language in languages() {
+            for (_, group) in language.tokens {
+                let count = resolved.highlights.iter().filter(|h| h.group == group);
+                assert!(count.count() <= 1, "{} is defined twice", group);
+            }
+        }
+    }
+}
@@ -1,7 +1,49 @@
-pub mod generators;
-pub mod highlights;
-pub mod decoder;
+//! Converts VSCode JSON themes to (Neo)Vim colorschemes.
+//!
+//! ```
+//! use djanho::{convert, Target, Theme};
+//!
+//! let theme: Theme = r##"{
+//!     "colors": { "editor.background": "#1e1e2e" },
+//!     "tokenColors": [
+//!         { "scope": "comment", "settings": { "foreground": "#6c7086" } }
+//!     ]
+//! }"##
+//! .parse()
+//! .unwrap();
+//!
+//! let output = convert(&theme, Target::Lua).unwrap();
+//! assert_eq!(output.files[0].path.to_str(), Some("generated.lua"));
+//! ```
+
+pub mod audit;
 pub mod colors;
+pub mod compiled;
+pub mod convert;
+pub mod decoder;
+pub mod defaults;
+pub mod error;
 pub mod generator;
-pub mod vimscript;
+pub mod generators;
+pub mod highlights;
+pub mod ir;
+pub mod languages;
 pub mod lua;
+pub mod patch;
+pub mod preview;
+pub mod report;
+pub mod settings;
+pub mod synthesize;
+pub mod theme;
+pub mod validate;
+pub mod vimscript;
+pub mod watch;
+
+pub use convert::{
+    convert, convert_pair, convert_resolved, convert_with, resolve_with, Options, OptionsBuilder,
+    Output, OutputFile, Target,
+};
+pub use error::{Error, Result};
+pub use patch::Patch;
+pub use settings::Settings;
+pub use theme::Theme;
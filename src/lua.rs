@@ -1,91 +1,417 @@
 use indoc::indoc;
 
-use crate::highlights::map_font_styles;
-use crate::{generator::ConfigGenerator, highlights::VimHighlight};
+use crate::{
+    colors,
+    generator::{indent, ConfigGenerator},
+    highlights::{Source, VimHighlight, TRANSPARENT_GROUPS},
+    ir::ResolvedTheme,
+    languages,
+    patch::SIMPLE_SCOPES,
+};
+
+const COMMENT: &str = "-- Colorscheme generated by https://github.com/arcticlimer/djanho\n";
+
+const HEADER: &str = indoc! {"
+    vim.cmd[[highlight clear]]
+    vim.g.colors_name = '{name}'
+
+    local highlight = function(group, bg, fg, attr, sp)
+        fg = fg and 'guifg=' .. fg or ''
+        bg = bg and 'guibg=' .. bg or ''
+        attr = attr and 'gui=' .. attr or ''
+        sp = sp and 'guisp=' .. sp or ''
+
+        vim.api.nvim_command('highlight ' .. group .. ' '.. fg .. ' ' .. bg .. ' '.. attr .. ' ' .. sp)
+    end
+
+    local link = function(target, group)
+        vim.api.nvim_command('highlight! link ' .. target .. ' '.. group)
+    end\n"
+};
+
+/// Ends the namespaced colorschemes, which are meant to be loaded with
+/// `dofile` and then applied to windows
+pub const EXPORTS: &str = indoc! {"
+
+    return {
+        ns = ns,
+        -- Shows the theme in `win`, the current window by default
+        apply = function(win)
+            vim.api.nvim_win_set_hl_ns(win or 0, ns)
+        end,
+    }\n"
+};
+
+/// Replaces `HEADER` when the highlights are set in the namespace of the
+/// theme, which `apply` shows in a window, instead of globally
+const NAMESPACED_HEADER: &str = indoc! {"
+    local ns = vim.api.nvim_create_namespace('{name}')
+
+    -- Adds the attributes to the group, like :highlight does
+    local highlight = function(group, bg, fg, attr, sp)
+        local spec = vim.api.nvim_get_hl(ns, { name = group, link = false })
+        spec.fg, spec.bg, spec.sp = fg or spec.fg, bg or spec.bg, sp or spec.sp
+        for name in (attr or ''):gmatch('[^,]+') do
+            spec[name] = name ~= 'NONE' or nil
+        end
+        vim.api.nvim_set_hl(ns, group, spec)
+    end
+
+    local link = function(target, group)
+        vim.api.nvim_set_hl(ns, target, { link = group })
+    end\n"
+};
+
+const OVERRIDES: &str = indoc! {"
+    -- Local tweaks that survive regenerating the colorscheme go in
+    -- lua/{name}/overrides.lua, which returns the groups to change, or a
+    -- function that fills them in given the palette:
+    --   return function(hl, palette) hl.Comment = { fg = palette.Color3, style = 'NONE' } end
+    local apply_overrides = function(palette)
+        package.loaded['{name}.overrides'] = nil
+        local ok, overrides = pcall(require, '{name}.overrides')
+        if not ok then
+            if not overrides:find(\"module '{name}.overrides' not found\", 1, true) then
+                error(overrides)
+            end
+            return
+        end
+
+        if type(overrides) == 'function' then
+            local hl = {}
+            overrides = overrides(hl, palette) or hl
+        end
+        for group, attrs in pairs(overrides) do
+            if attrs.link then
+                link(group, attrs.link)
+            else
+                local color = function(key) return palette[attrs[key]] or attrs[key] end
+                highlight(group, color('bg'), color('fg'), attrs.style, color('sp'))
+            end
+        end
+    end\n"
+};
+
+const MODULE: &str = indoc! {"
+    local M = {}
+
+    -- Options changed through `M.setup`. The italic_ and bold_ ones keep the
+    -- italics and bold of each category of syntax groups.
+    M.options = {
+    {options}
+        transparent = false,
+        dim_inactive = false,
+    }
+
+    M.setup = function(opts)
+        M.options = vim.tbl_extend('force', M.options, opts or {})
+    end
+
+    -- Category of the groups with italics or bold
+    local categories = {
+    {categories}
+    }
+
+    local transparent_groups = {
+    {transparent_groups}
+    }
+
+    -- Drops the text styles turned off for the category of `group`
+    local style = function(group, attr)
+        local category = categories[group]
+        if not attr or not category then
+            return attr
+        end
+
+        local attrs = {}
+        for name in attr:gmatch('[^,]+') do
+            if M.options[name .. '_' .. category] ~= false then
+                table.insert(attrs, name)
+            end
+        end
+        return #attrs > 0 and table.concat(attrs, ',') or nil
+    end\n"
+};
+
+const LOAD: &str = indoc! {"
+    local set_highlight = highlight
+    highlight = function(group, bg, fg, attr, sp)
+        if M.options.transparent and transparent_groups[group] then
+            bg = 'NONE'
+        end
+        set_highlight(group, bg, fg, style(group, attr), sp)
+    end\n"
+};
+
+const MODULE_APPLY: &str = indoc! {"
+
+    -- Shows the theme in `win`, the current window by default
+    M.apply = function(win)
+        vim.api.nvim_win_set_hl_ns(win or 0, vim.api.nvim_create_namespace('{name}'))
+    end\n"
+};
 
 #[derive(Debug)]
 pub struct LuaGenerator {
-    // This should be a mutable reference, but the borrow checker
-    // is too strong, I can't beat him :(
-    buffer: String,
+    /// Name of the colorscheme, which the user overrides module is named after
+    pub name: String,
+    /// Whether to render a `lua/<name>/init.lua` module with `setup` and
+    /// `load` functions instead of a colorscheme
+    pub module: bool,
+    /// Whether the highlights are set in the namespace of the theme, which an
+    /// `apply` function shows in a window, instead of globally
+    pub namespace: bool,
 }
 
-impl Default for LuaGenerator {
-    fn default() -> LuaGenerator {
-        let mut _self = LuaGenerator {
-            buffer: String::new(),
-        };
-        _self.buffer += indoc! {"
-            -- Colorscheme generated by https://github.com/arcticlimer/djanho
-            vim.cmd[[highlight clear]]
+impl LuaGenerator {
+    pub fn new<S: Into<String>>(name: S) -> LuaGenerator {
+        LuaGenerator {
+            name: name.into(),
+            module: false,
+            namespace: false,
+        }
+    }
 
-            local highlight = function(group, bg, fg, attr)
-                fg = fg and 'guifg=' .. fg or ''
-                bg = bg and 'guibg=' .. bg or ''
-                attr = attr and 'gui=' .. attr or ''
+    pub fn module<S: Into<String>>(name: S) -> LuaGenerator {
+        LuaGenerator {
+            name: name.into(),
+            module: true,
+            namespace: false,
+        }
+    }
 
-                vim.api.nvim_command('highlight ' .. group .. ' '.. fg .. ' ' .. bg .. ' '.. attr)
-            end
+    pub fn with_namespace(mut self, namespace: bool) -> LuaGenerator {
+        self.namespace = namespace;
+        self
+    }
 
-            local link = function(target, group)
-                vim.api.nvim_command('highlight! link ' .. target .. ' '.. group)
-            end\n"
+    fn header(&self) -> String {
+        let header = if self.namespace {
+            NAMESPACED_HEADER
+        } else {
+            HEADER
         };
-        _self
+        (header.to_string() + "\n" + OVERRIDES).replace("{name}", &escape(&self.name))
+    }
+
+    /// Ends a colorscheme that isn't a module
+    fn footer(&self) -> &'static str {
+        if self.namespace {
+            EXPORTS
+        } else {
+            ""
+        }
+    }
+
+    /// Wraps the code applying the theme in the `load` function of a module
+    fn render_module(&self, load: &str, themes: &[&ResolvedTheme]) -> String {
+        let options: Vec<String> = ["italic", "bold"]
+            .iter()
+            .flat_map(|style| {
+                SIMPLE_SCOPES
+                    .iter()
+                    .map(move |(category, _)| format!("    {}_{} = true,", style, category))
+            })
+            .collect();
+
+        let mut categories: Vec<String> = Vec::new();
+        for highlight in themes.iter().flat_map(|theme| &theme.highlights) {
+            let styled = highlight
+                .text_style
+                .as_deref()
+                .is_some_and(|style| style.contains("italic") || style.contains("bold"));
+            if let (true, Some(category)) = (styled, category(highlight)) {
+                let entry = format!("    [{}] = '{}',", quote(&highlight.group), category);
+                if !categories.contains(&entry) {
+                    categories.push(entry);
+                }
+            }
+        }
+
+        let transparent_groups: Vec<String> = TRANSPARENT_GROUPS
+            .iter()
+            .map(|group| format!("    {} = true,", group))
+            .collect();
+
+        let mut buffer = COMMENT.to_string();
+        buffer += &MODULE
+            .replace("{options}\n", &(options.join("\n") + "\n"))
+            .replace("{categories}\n", &lines(&categories))
+            .replace("{transparent_groups}\n", &lines(&transparent_groups));
+        buffer += "\nM.load = function()\n";
+        buffer += &indent(&self.header(), 4);
+        buffer += "\n";
+        buffer += &indent(LOAD, 4);
+        buffer += &indent(load, 4);
+        buffer += "end\n";
+        if self.namespace {
+            buffer += &MODULE_APPLY.replace("{name}", &escape(&self.name));
+        }
+        buffer += "\nreturn M\n";
+        buffer
     }
 }
 
 impl ConfigGenerator for LuaGenerator {
-    fn collect(&self) -> String {
-        self.buffer.to_string()
+    fn render(&self, theme: &ResolvedTheme) -> String {
+        if self.module {
+            return self.render_module(&body(theme, true), &[theme]);
+        }
+        COMMENT.to_string() + &self.header() + &body(theme, false) + self.footer()
     }
-    fn link(&mut self, group: &str, target: &str) {
-        self.buffer += link(group, target).as_str()
+
+    fn render_pair(&self, dark: &ResolvedTheme, light: &ResolvedTheme) -> String {
+        let mut buffer = String::new();
+        buffer += "\nif vim.o.background == 'light' then\n";
+        buffer += &indent(body(light, self.module).trim_start(), 4);
+        buffer += "else\n";
+        buffer += &indent(body(dark, self.module).trim_start(), 4);
+        buffer += "end\n";
+
+        if self.module {
+            return self.render_module(&buffer, &[dark, light]);
+        }
+        COMMENT.to_string() + &self.header() + &buffer + self.footer()
     }
-    fn highlight(&mut self, options: &VimHighlight) {
-        self.buffer += &highlight(options)
+}
+
+/// The colors file loading the module, reloading it when the colorscheme is
+/// set again so a regenerated module is picked up with the same options
+pub fn loader(name: &str) -> String {
+    indoc! {"
+        -- Colorscheme generated by https://github.com/arcticlimer/djanho
+        local loaded = package.loaded['{name}']
+        package.loaded['{name}'] = nil
+
+        local theme = require('{name}')
+        if loaded then
+            theme.setup(loaded.options)
+        end
+        theme.load()
+    "}
+    .replace("{name}", &escape(name))
+}
+
+/// The `editor.tokenColorCustomizations` category (e.g. `comments`) of the
+/// scopes a highlight comes from
+fn category(highlight: &VimHighlight) -> Option<&'static str> {
+    highlight.sources.iter().find_map(|source| {
+        let scope = match source {
+            Source::Scope(selector) => selector.split_whitespace().last()?,
+            _ => return None,
+        };
+        SIMPLE_SCOPES
+            .iter()
+            .find(|(_, selectors)| {
+                selectors
+                    .iter()
+                    .any(|selector| languages::selector_matches(selector, scope))
+            })
+            .map(|(category, _)| *category)
+    })
+}
+
+fn lines(lines: &[String]) -> String {
+    lines.iter().map(|line| format!("{}\n", line)).collect()
+}
+
+fn body(theme: &ResolvedTheme, module: bool) -> String {
+    let mut buffer = String::new();
+
+    if let Some(note) = theme.metadata.synthesized_note() {
+        buffer += &format!("\n-- {}\n", note);
+    }
+
+    // A table rather than a local per color, as LuaJIT allows 200 locals
+    buffer += "\nlocal palette = {\n";
+    for color in &theme.palette {
+        buffer += &format!("    {} = {},\n", color.name, quote(&color.color));
     }
-    fn variable(&mut self, name: String, color: String) {
-        self.buffer += &create_variable(name, color)
+    buffer += "}\n";
+
+    buffer += "\n";
+    for options in &theme.highlights {
+        buffer += &highlight(options)
+    }
+
+    buffer += "\n";
+    for vim_link in &theme.links {
+        buffer += &link(&vim_link.group, &vim_link.target)
     }
-    fn newline(&mut self) {
-        self.buffer += "\n"
+
+    if module {
+        let (_, background) = theme.normal_colors();
+        if let Ok(background) = colors::from_hex_string(&background) {
+            let inactive = colors::to_rgb_hex_string(colors::darken(background, 0.04));
+            buffer += "\nif M.options.dim_inactive then\n";
+            buffer += &format!("    set_highlight('NormalNC', '{}', nil, nil)\n", inactive);
+            buffer += "end\n";
+        }
     }
+
+    // The palette is local to the variant, so each one applies the overrides
+    buffer += "\napply_overrides(palette)\n";
+
+    buffer
 }
 
 fn highlight(options: &VimHighlight) -> String {
     let guibg = mk_option(&options.background);
     let guifg = mk_option(&options.foreground);
 
-    let text_style = &map_font_styles(&options.text_style).unwrap_or_else(|| "nil".to_string());
-    let text_style = if text_style == "nil" {
-        text_style.clone()
-    } else {
-        format!("'{}'", text_style)
+    let text_style = match &options.text_style {
+        Some(style) => quote(style),
+        None => "nil".to_string(),
     };
 
     if guibg == "nil" && guifg == "nil" && text_style == "nil" {
         return String::new();
     }
 
+    let guisp = match &options.special {
+        Some(special) => format!(", palette.{}", special),
+        None => String::new(),
+    };
+
     format!(
-        "highlight('{}', {}, {}, {})\n",
-        options.group, guibg, guifg, text_style
+        "highlight({}, {}, {}, {}{})\n",
+        quote(&options.group),
+        guibg,
+        guifg,
+        text_style,
+        guisp
     )
 }
 
 fn mk_option(value: &Option<String>) -> String {
     if let Some(option) = value {
-        option.to_string()
+        format!("palette.{}", option)
     } else {
         "nil".to_owned()
     }
 }
 
 fn link(group: &str, target: &str) -> String {
-    format!("link('{}', '{}')\n", group, target)
+    format!("link({}, {})\n", quote(group), quote(target))
+}
+
+/// Escapes `value` to be used inside single or double quoted strings
+pub fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => escaped += "\\\\",
+            '\'' => escaped += "\\'",
+            '"' => escaped += "\\\"",
+            '\n' => escaped += "\\n",
+            '\r' => escaped += "\\r",
+            c => escaped.push(c),
+        }
+    }
+    escaped
 }
 
-fn create_variable(name: String, color: String) -> String {
-    format!("local {} = '{}'\n", name, color)
+/// Quotes `value` as a single quoted string
+pub fn quote(value: &str) -> String {
+    format!("'{}'", escape(value))
 }
@@ -1,9 +1,15 @@
-use clap::clap_app;
-use std::{fs::File, io::Write};
+use clap::{clap_app, ArgMatches};
+use std::{
+    fmt::Display,
+    fs,
+    path::{Path, PathBuf},
+    process,
+};
 
 use djanho::{
-    decoder, generator::ConfigGenerator, generators, lua::LuaGenerator,
-    vimscript::VimscriptGenerator,
+    audit::{self, Thresholds},
+    convert_pair, convert_resolved, convert_with, preview, report, resolve_with, watch, Options,
+    Output, Patch, Settings, Target, Theme,
 };
 
 fn main() {
@@ -12,32 +18,280 @@ fn main() {
         (author: "Vinícius Müller <vinigm.nho@gmail.com>")
         (about: "Convert VSCode's JSON themes to Vimscript/Lua themes")
         (@arg FILENAME: +required "Sets the input file to use")
-        (@arg OUTPUT: -o --output "Sets the output file to use")
+        (@arg OUTPUT: -o --output +takes_value "Sets the output file to use")
         (@arg LUA_CONFIG: -l --lua "Whether to output the file in Lua")
+        (@arg MODULE: --module "Outputs a Lua module with setup() and load() and the colors file loading it, under the output directory")
+        (@arg COMPILED: --compiled conflicts_with[MODULE] "Outputs Lua with a direct nvim_set_hl call per group, which loads faster")
+        (@arg NAMESPACE: --namespace "Sets the Lua highlights in a namespace, shown in a window by the returned apply(win)")
+        (@arg NAME: -n --name +takes_value "Name of the colorscheme, the output file name by default")
+        (@arg DUMP_IR: --("dump-ir") conflicts_with[LIGHT] "Prints the resolved theme as JSON instead of generating a config")
+        (@arg REPORT: --report conflicts_with[LIGHT INVERT TRANSPARENT] "Prints the scopes, colors and groups the conversion left out instead of generating a config")
+        (@arg REPORT_FORMAT: --("report-format") +takes_value possible_value[text json] "Format of the report, text by default")
+        (@arg PATCH: --patch +takes_value "Overlay of VSCode color customizations, as JSON or TOML, merged into the theme")
+        (@arg SETTINGS: --settings +takes_value "VSCode settings.json whose color customizations are applied to the theme")
+        (@arg LIGHT: --light +takes_value "Light variant of the theme, used when background is light")
+        (@arg TRANSPARENT: --transparent "Clears the background of Normal, SignColumn, NormalFloat and the like")
+        (@arg INVERT: --invert conflicts_with[LIGHT] "Synthesizes a light variant of a dark theme, or a dark variant of a light one")
+        (@arg WATCH: -w --watch "Regenerates the output whenever the theme or its includes change")
+        (@arg NOTIFY: --notify +takes_value "Neovim socket or named pipe told to source the output after generating it")
+        (@setting SubcommandsNegateReqs)
+        (@subcommand preview =>
+            (about: "Renders code samples highlighted with the converted theme")
+            (@arg FILENAME: +required "Sets the input file to use")
+            (@arg OUTPUT: -o --output +takes_value "Sets the output file to use")
+            (@arg FORMAT: -f --format +takes_value possible_value[html svg] "Format of the preview, html by default")
+            (@arg PATCH: --patch +takes_value "Overlay of VSCode color customizations, as JSON or TOML, merged into the theme")
+            (@arg SETTINGS: --settings +takes_value "VSCode settings.json whose color customizations are applied to the theme")
+        )
+        (@subcommand audit =>
+            (about: "Reports the groups with too little contrast against their background")
+            (@arg FILENAME: +required "Sets the input file to use")
+            (@arg WCAG: --wcag +takes_value "Minimum WCAG 2 contrast ratio, 4.5 by default")
+            (@arg APCA: --apca +takes_value "Minimum absolute APCA contrast, 60 by default")
+            (@arg FIX: --fix "Adjusts the lightness of the failing groups and writes the colorscheme")
+            (@arg OUTPUT: -o --output +takes_value "Sets the output file to use with --fix")
+            (@arg LUA_CONFIG: -l --lua "Whether to output the file in Lua")
+            (@arg PATCH: --patch +takes_value "Overlay of VSCode color customizations, as JSON or TOML, merged into the theme")
+            (@arg SETTINGS: --settings +takes_value "VSCode settings.json whose color customizations are applied to the theme")
+        )
     )
     .get_matches();
 
-    let has_lua = matches.is_present("LUA_CONFIG");
-    let default_extension = if has_lua { "lua" } else { "vim" };
+    match matches.subcommand() {
+        ("preview", Some(matches)) => return render_preview(matches),
+        ("audit", Some(matches)) => return run_audit(matches),
+        _ => {}
+    }
+
+    if matches.is_present("DUMP_IR") {
+        let filepath = matches.value_of("FILENAME").unwrap();
+        let theme = read_theme(&matches, filepath).unwrap_or_else(|err| fail(err));
+        println!("{}", resolve_with(&theme, &options(&matches)).to_json());
+        return;
+    }
+
+    if matches.is_present("REPORT") {
+        let filepath = matches.value_of("FILENAME").unwrap();
+        let theme = read_theme(&matches, filepath).unwrap_or_else(|err| fail(err));
+        let report = report::report(&theme);
+        match matches.value_of("REPORT_FORMAT") {
+            Some("json") => println!("{}", report.to_json()),
+            _ => print!("{}", report),
+        }
+        return;
+    }
+
+    if matches.is_present("WATCH") {
+        watch::watch(|| match generate(&matches) {
+            Ok(sources) => Ok(sources),
+            Err(err) => {
+                eprintln!("{}", err);
+                Err(input_paths(&matches))
+            }
+        })
+    }
+
+    generate(&matches).unwrap_or_else(|err| fail(err));
+}
+
+fn options(matches: &ArgMatches) -> Options {
+    Options::builder()
+        .name(colorscheme_name(matches))
+        .invert_variant(matches.is_present("INVERT"))
+        .transparent(matches.is_present("TRANSPARENT"))
+        .lua_module(matches.is_present("MODULE"))
+        .compiled(matches.is_present("COMPILED"))
+        .namespace(matches.is_present("NAMESPACE"))
+        .build()
+}
+
+/// Generates the colorscheme, returning the files the theme was read from
+fn generate(matches: &ArgMatches) -> Result<Vec<PathBuf>, String> {
+    let target = if ["LUA_CONFIG", "MODULE", "COMPILED", "NAMESPACE"]
+        .iter()
+        .any(|option| matches.is_present(option))
+    {
+        Target::Lua
+    } else {
+        Target::Vimscript
+    };
 
     let filepath = matches.value_of("FILENAME").unwrap();
-    let filename = format!("generated.{}", default_extension);
-    let output_path = matches
-        .value_of("OUTPUT")
-        .unwrap_or_else(|| filename.as_str());
+    let theme = read_theme(matches, filepath)?;
+
+    let options = options(matches);
+    let mut sources = theme.sources().to_vec();
+    for option in &["SETTINGS", "PATCH"] {
+        if let Some(filepath) = matches.value_of(option) {
+            sources.push(filepath.into());
+        }
+    }
+    let output = match matches.value_of("LIGHT") {
+        Some(light_filepath) => {
+            let light = read_theme(matches, light_filepath)?;
+            sources.extend_from_slice(light.sources());
+            convert_pair(&theme, &light, target, &options)
+        }
+        None => convert_with(&theme, target, &options),
+    }
+    .map_err(|err| err.to_string())?;
+    warn_invalid_colors(&output);
+
+    let mut paths = Vec::new();
+    let multiple = output.files.len() > 1;
+    for file in output.files {
+        // Several files are written under the output directory
+        let path = match matches.value_of("OUTPUT") {
+            Some(output_path) if multiple => Path::new(output_path).join(file.path),
+            Some(output_path) => output_path.into(),
+            None => file.path,
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|err| format!("Unable to create {}: {}", parent.display(), err))?;
+        }
+        fs::write(&path, file.contents)
+            .map_err(|err| format!("Unable to write the generated config: {}", err))?;
+        paths.push(path);
+    }
+
+    // The first file is the one `:colorscheme` sources
+    if let (Some(target), Some(path)) = (matches.value_of("NOTIFY"), paths.first()) {
+        watch::notify(Path::new(target), path)
+            .map_err(|err| format!("Unable to notify {}: {}", target, err))?;
+    }
+
+    Ok(sources)
+}
+
+/// Tells which colors of the theme couldn't be parsed, as they are left out
+fn warn_invalid_colors(output: &Output) {
+    for invalid in &output.invalid_colors {
+        eprintln!("Ignoring {}", invalid);
+    }
+}
+
+/// The files given on the command line, watched along with the previous ones
+/// when the theme couldn't be read and so its includes aren't known
+fn input_paths(matches: &ArgMatches) -> Vec<PathBuf> {
+    ["FILENAME", "LIGHT", "SETTINGS", "PATCH"]
+        .iter()
+        .filter_map(|option| matches.value_of(option))
+        .map(PathBuf::from)
+        .collect()
+}
+
+/// Reads the theme at `filepath`, applying the `--settings` customizations and
+/// then the `--patch` overlay to it
+fn read_theme(matches: &ArgMatches, filepath: &str) -> Result<Theme, String> {
+    let mut theme = Theme::from_path(filepath).map_err(|err| err.to_string())?;
+    if let Some(settings_filepath) = matches.value_of("SETTINGS") {
+        let settings = Settings::from_path(settings_filepath).map_err(|err| err.to_string())?;
+        theme
+            .apply_settings(&settings)
+            .map_err(|err| err.to_string())?;
+    }
+    if let Some(patch_filepath) = matches.value_of("PATCH") {
+        let patch = Patch::from_path(patch_filepath).map_err(|err| err.to_string())?;
+        theme.apply_patch(&patch).map_err(|err| err.to_string())?;
+    }
+    Ok(theme)
+}
+
+fn render_preview(matches: &ArgMatches) {
+    let filepath = matches.value_of("FILENAME").unwrap();
+    let theme = read_theme(matches, filepath).unwrap_or_else(|err| fail(err));
+    let resolved = theme.resolve();
+
+    let format = matches.value_of("FORMAT").unwrap_or("html");
+    let preview = match format {
+        "svg" => preview::render_svg(&resolved),
+        _ => preview::render_html(&resolved),
+    };
+
+    let filename = format!("preview.{}", format);
+    let output_path = matches.value_of("OUTPUT").unwrap_or(filename.as_str());
+    fs::write(output_path, preview)
+        .unwrap_or_else(|err| fail(format!("Unable to write the preview: {}", err)))
+}
+
+fn run_audit(matches: &ArgMatches) {
+    let filepath = matches.value_of("FILENAME").unwrap();
+    let theme = read_theme(matches, filepath).unwrap_or_else(|err| fail(err));
+    let mut resolved = theme.resolve();
+
+    let mut thresholds = Thresholds::default();
+    if let Some(wcag) = matches.value_of("WCAG") {
+        thresholds.wcag = wcag.parse().unwrap_or_else(|err| fail(err));
+    }
+    if let Some(apca) = matches.value_of("APCA") {
+        thresholds.apca = apca.parse().unwrap_or_else(|err| fail(err));
+    }
+
+    let failing: Vec<_> = audit::audit(&resolved)
+        .into_iter()
+        .filter(|finding| !finding.passes(&thresholds))
+        .collect();
+
+    for finding in &failing {
+        println!(
+            "{:<24} {} on {}  WCAG {:>5.2}  APCA {:>6.1}",
+            finding.group, finding.foreground, finding.background, finding.wcag, finding.apca
+        );
+    }
+    println!(
+        "{} group(s) below WCAG {} / APCA {}",
+        failing.len(),
+        thresholds.wcag,
+        thresholds.apca
+    );
+
+    if !matches.is_present("FIX") {
+        if !failing.is_empty() {
+            process::exit(1)
+        }
+        return;
+    }
 
-    let theme = decoder::parse_file(filepath.to_string());
+    let fixed = audit::fix(&mut resolved, &thresholds);
+    println!("Adjusted {} group(s): {}", fixed.len(), fixed.join(", "));
 
-    // Select generator and generate config
-    let mut generator: Box<dyn ConfigGenerator> = if has_lua {
-        Box::new(LuaGenerator::default())
+    let target = if matches.is_present("LUA_CONFIG") {
+        Target::Lua
     } else {
-        Box::new(VimscriptGenerator::default())
+        Target::Vimscript
     };
-    generators::generate_config(theme, &mut generator);
-    let config = generator.collect();
+    let options = Options::builder().name(colorscheme_name(matches)).build();
+    let output = convert_resolved(&resolved, target, &options).unwrap_or_else(|err| fail(err));
+    warn_invalid_colors(&output);
+
+    for file in output.files {
+        let path = match matches.value_of("OUTPUT") {
+            Some(output_path) => output_path.into(),
+            None => file.path,
+        };
+        fs::write(path, file.contents)
+            .unwrap_or_else(|err| fail(format!("Unable to write the generated config: {}", err)))
+    }
+}
+
+/// Names the colorscheme after the output file, which `:colorscheme` looks for,
+/// unless it is named explicitly
+fn colorscheme_name(matches: &ArgMatches) -> String {
+    if let Some(name) = matches.value_of("NAME") {
+        return name.to_string();
+    }
+    // Modules are written under the output directory instead
+    matches
+        .value_of("OUTPUT")
+        .filter(|_| !matches.is_present("MODULE"))
+        .and_then(|path| Path::new(path).file_stem())
+        .and_then(|stem| stem.to_str())
+        .map(str::to_string)
+        .unwrap_or_else(|| Options::default().name().to_string())
+}
 
-    let mut f = File::create(output_path).expect("Unable to create file");
-    f.write_all(config.as_bytes())
-        .expect("Unable to write the generated config")
+fn fail<E: Display>(err: E) -> ! {
+    eprintln!("{}", err);
+    process::exit(1)
 }
@@ -0,0 +1,321 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use json_comments::StripComments;
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::Value;
+
+use crate::{
+    decoder::{
+        VSCodeHighlight, VSCodeScope, VSCodeScopeSettings, VSCodeSemanticStyle, VSCodeTheme,
+    },
+    error::{Error, Result},
+};
+
+/// The scopes VSCode colors for each of the simple keys of
+/// `editor.tokenColorCustomizations`
+pub const SIMPLE_SCOPES: &[(&str, &[&str])] = &[
+    ("comments", &["comment", "punctuation.definition.comment"]),
+    ("strings", &["string", "meta.embedded.assembly"]),
+    (
+        "keywords",
+        &["keyword", "keyword.control", "storage", "storage.type"],
+    ),
+    ("numbers", &["constant.numeric"]),
+    (
+        "types",
+        &[
+            "entity.name.type",
+            "entity.name.class",
+            "support.type",
+            "support.class",
+        ],
+    ),
+    ("functions", &["entity.name.function", "support.function"]),
+    ("variables", &["variable", "entity.name.variable"]),
+];
+
+/// Overrides for a theme, in the format of VSCode's
+/// `workbench.colorCustomizations`, `editor.tokenColorCustomizations` and
+/// `editor.semanticTokenColorCustomizations` settings. Sections such as `"[Theme Name]": { ... }` only apply to the
+/// themes they name.
+#[derive(Deserialize, Debug, Default, Clone)]
+pub struct Patch {
+    #[serde(rename = "workbench.colorCustomizations", default)]
+    pub colors: BTreeMap<String, Value>,
+    #[serde(rename = "editor.tokenColorCustomizations", default)]
+    pub tokens: BTreeMap<String, Value>,
+    #[serde(rename = "editor.semanticTokenColorCustomizations", default)]
+    pub semantic_tokens: BTreeMap<String, Value>,
+}
+
+impl Patch {
+    /// Reads a patch, decoded as TOML when the file has a `.toml` extension
+    /// and as JSON with comments otherwise
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Patch> {
+        let path = path.as_ref();
+        let body = fs::read_to_string(path).map_err(Error::io(path))?;
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("toml") => Patch::from_toml(&body),
+            _ => Patch::from_json(&body),
+        }
+    }
+
+    pub fn from_json(body: &str) -> Result<Patch> {
+        serde_json::from_reader(StripComments::new(body.as_bytes())).map_err(Error::json("patch"))
+    }
+
+    pub fn from_toml(body: &str) -> Result<Patch> {
+        toml::from_str(body).map_err(Error::Toml)
+    }
+
+    /// Merges the patch into `theme`. Its colors replace the theme ones, and
+    /// its token rules are added after the theme ones, so they take
+    /// precedence. Fails without changing `theme` when an entry is malformed.
+    pub fn apply(&self, theme: &mut VSCodeTheme) -> Result<()> {
+        let name = theme.name.clone();
+        self.apply_as(theme, name.as_deref())
+    }
+
+    /// Merges the patch into `theme`, applying the sections that name `name`
+    pub fn apply_as(&self, theme: &mut VSCodeTheme, name: Option<&str>) -> Result<()> {
+        let mut colors = Vec::new();
+        for (key, value) in sections(&self.colors, name) {
+            let color: String = decode(value, || format!("{}[\"{}\"]", COLORS, key))?;
+            colors.push((key.clone(), color));
+        }
+
+        // Simple keys come first, as textMateRules are more specific
+        let token_sections = sections(&self.tokens, name);
+        let mut rules = Vec::new();
+        for &(key, value) in &token_sections {
+            let scopes = match SIMPLE_SCOPES.iter().find(|(simple, _)| simple == key) {
+                Some((_, scopes)) => scopes,
+                None => continue,
+            };
+            let settings = match value {
+                Value::String(color) => VSCodeScopeSettings {
+                    foreground: Some(color.clone()),
+                    background: None,
+                    font_style: None,
+                },
+                value => decode(value, || format!("{}.{}", TOKENS, key))?,
+            };
+            rules.push(VSCodeHighlight {
+                scope: Some(VSCodeScope::Multiple(
+                    scopes.iter().map(|scope| scope.to_string()).collect(),
+                )),
+                settings,
+            });
+        }
+        let mut semantic_rules = Vec::new();
+        for &(key, value) in &token_sections {
+            match key.as_str() {
+                "textMateRules" => {
+                    let text_mate_rules: Vec<Value> =
+                        decode(value, || format!("{}.textMateRules", TOKENS))?;
+                    for (idx, rule) in text_mate_rules.iter().enumerate() {
+                        rules.push(decode(rule, || {
+                            format!("{}.textMateRules[{}]", TOKENS, idx)
+                        })?);
+                    }
+                }
+                "semanticTokenColors" => semantic_rules.extend(semantic_tokens(
+                    value,
+                    &format!("{}.semanticTokenColors", TOKENS),
+                )?),
+                _ => {}
+            }
+        }
+
+        let semantic_sections = sections(&self.semantic_tokens, name);
+        for &(key, value) in &semantic_sections {
+            if key.as_str() == "rules" {
+                semantic_rules.extend(semantic_tokens(
+                    value,
+                    &format!("{}.rules", SEMANTIC_TOKENS),
+                )?);
+            }
+        }
+        // VSCode doesn't color semantic tokens at all when they are disabled
+        let enabled = semantic_sections
+            .iter()
+            .rev()
+            .find(|(key, _)| key.as_str() == "enabled")
+            .map(|(_, value)| value.as_bool() != Some(false));
+
+        theme
+            .colors
+            .get_or_insert_with(Default::default)
+            .extend(colors);
+        theme.tokens.extend(rules);
+        theme.semantic_tokens.extend(semantic_rules);
+        if enabled == Some(false) {
+            theme.semantic_tokens.clear();
+        }
+        Ok(())
+    }
+}
+
+const COLORS: &str = "workbench.colorCustomizations";
+const TOKENS: &str = "editor.tokenColorCustomizations";
+const SEMANTIC_TOKENS: &str = "editor.semanticTokenColorCustomizations";
+
+/// Decodes the `selector: style` rules of `rules`, found at `key`
+fn semantic_tokens(rules: &Value, key: &str) -> Result<Vec<(String, VSCodeSemanticStyle)>> {
+    let rules: BTreeMap<String, Value> = decode(rules, || key.to_string())?;
+    rules
+        .into_iter()
+        .map(|(selector, style)| {
+            let style = decode(&style, || format!("{}[\"{}\"]", key, selector))?;
+            Ok((selector, style))
+        })
+        .collect()
+}
+
+/// Decodes the entry of the patch at `key`
+fn decode<T: DeserializeOwned>(value: &Value, key: impl FnOnce() -> String) -> Result<T> {
+    T::deserialize(value).map_err(|source| Error::InvalidPatch { key: key(), source })
+}
+
+/// Flattens the entries of `customizations` that apply to the theme `name`,
+/// with the ones of the theme specific sections last
+fn sections<'a>(
+    customizations: &'a BTreeMap<String, Value>,
+    name: Option<&str>,
+) -> Vec<(&'a String, &'a Value)> {
+    let mut entries: Vec<(&String, &Value)> = customizations
+        .iter()
+        .filter(|(key, _)| !key.starts_with('['))
+        .collect();
+
+    for (key, value) in customizations {
+        if !key.starts_with('[') || !applies_to(key, name) {
+            continue;
+        }
+        if let Value::Object(section) = value {
+            entries.extend(section.iter());
+        }
+    }
+
+    entries
+}
+
+/// Whether a section key such as `[One Dark][*Light*]` names the theme `name`
+fn applies_to(key: &str, name: Option<&str>) -> bool {
+    let name = match name {
+        Some(name) => name,
+        None => return false,
+    };
+    key.split(['[', ']'])
+        .filter(|pattern| !pattern.is_empty())
+        .any(|pattern| glob_matches(pattern, name))
+}
+
+/// Matches `name` against a pattern where `*` stands for any text
+fn glob_matches(pattern: &str, name: &str) -> bool {
+    let parts: Vec<&str> = pattern.split('*').collect();
+    if parts.len() == 1 {
+        return pattern == name;
+    }
+
+    let (first, last) = (parts[0], parts[parts.len() - 1]);
+    if !name.starts_with(first) || !name[first.len()..].ends_with(last) {
+        return false;
+    }
+    let mut rest = &name[first.len()..name.len() - last.len()];
+    for part in &parts[1..parts.len() - 1] {
+        match rest.find(part) {
+            Some(idx) => rest = &rest[idx + part.len()..],
+            None => return false,
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decoder;
+
+    #[test]
+    fn can_apply_customizations() {
+        let mut theme = decoder::parse_reader(
+            r##"{
+                "name": "One Dark Pro",
+                "colors": { "editor.background": "#282c34" },
+                "tokenColors": [
+                    { "scope": "comment", "settings": { "foreground": "#7f848e", "fontStyle": "italic" } }
+                ]
+            }"##
+            .as_bytes(),
+        )
+        .unwrap();
+        let patch = Patch::from_toml(
+            r##"
+            ["workbench.colorCustomizations"]
+            "editor.foreground" = "#abb2bf"
+            "[One Dark*]" = { "editor.background" = "#1e2127" }
+            "[Monokai]" = { "editor.background" = "#272822" }
+
+            ["editor.tokenColorCustomizations"]
+            comments = "#5c6370"
+            textMateRules = [{ scope = "comment", settings = { fontStyle = "" } }]
+            "##,
+        )
+        .unwrap();
+        patch.apply(&mut theme).unwrap();
+
+        let colors = theme.colors.as_ref().unwrap();
+        assert_eq!(colors["editor.background"], "#1e2127");
+        assert_eq!(colors["editor.foreground"], "#abb2bf");
+
+        assert_eq!(theme.tokens.len(), 3);
+        assert_eq!(
+            theme.tokens[1].settings.foreground.as_deref(),
+            Some("#5c6370")
+        );
+        assert_eq!(theme.tokens[2].settings.font_style.as_deref(), Some(""));
+
+        assert!(glob_matches("*Light*", "Solarized Light (High Contrast)"));
+        assert!(!glob_matches("One Dark", "One Dark Pro"));
+
+        let err = Patch::from_path("missing-patch.json").unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("Could not read missing-patch.json"));
+        let err = Patch::from_json("{").unwrap_err();
+        assert!(err.to_string().starts_with("Could not decode the patch"));
+    }
+
+    #[test]
+    fn can_reject_malformed_entries() {
+        let mut theme = decoder::parse_reader(r#"{ "tokenColors": [] }"#.as_bytes()).unwrap();
+        let mut invalid_key = |json: &str| match Patch::from_json(json).unwrap().apply(&mut theme) {
+            Err(Error::InvalidPatch { key, .. }) => key,
+            result => panic!("{} was applied: {:?}", json, result),
+        };
+
+        assert_eq!(
+            invalid_key(
+                r##"{ "editor.tokenColorCustomizations": { "textMateRules": [
+                    { "scope": "comment", "settings": { "foreground": "#5c6370" } },
+                    { "scope": "string", "settings": "#98c379" }
+                ] } }"##
+            ),
+            "editor.tokenColorCustomizations.textMateRules[1]"
+        );
+        assert_eq!(
+            invalid_key(r#"{ "editor.tokenColorCustomizations": { "comments": 5 } }"#),
+            "editor.tokenColorCustomizations.comments"
+        );
+        assert_eq!(
+            invalid_key(r#"{ "workbench.colorCustomizations": { "editor.background": [] } }"#),
+            "workbench.colorCustomizations[\"editor.background\"]"
+        );
+        // Nothing is applied from a patch that fails
+        assert!(theme.tokens.is_empty());
+        assert!(theme.colors.is_none());
+    }
+}
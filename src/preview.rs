@@ -0,0 +1,353 @@
+use crate::ir::ResolvedTheme;
+
+/// A code sample, as (highlight group, text) spans. An empty group means
+/// the text is rendered with `Normal`.
+pub struct Sample {
+    pub language: &'static str,
+    pub spans: &'static [(&'static str, &'static str)],
+}
+
+pub const SAMPLES: &[Sample] = &[
+    Sample {
+        language: "Rust",
+        spans: &[
+            ("Comment", "// Sums the even numbers of a slice\n"),
+            ("Keyword", "pub fn"),
+            ("", " "),
+            ("Function", "sum_even"),
+            ("", "("),
+            ("TSParameter", "numbers"),
+            ("Operator", ": &"),
+            ("Type", "[i64]"),
+            ("", ") "),
+            ("Operator", "->"),
+            ("", " "),
+            ("Type", "i64"),
+            ("", " {\n    "),
+            ("Identifier", "numbers"),
+            ("", "."),
+            ("TSMethod", "iter"),
+            ("", "()."),
+            ("TSMethod", "filter"),
+            ("", "(|"),
+            ("TSParameter", "n"),
+            ("", "| "),
+            ("TSParameter", "n"),
+            ("Operator", " % "),
+            ("Number", "2"),
+            ("Operator", " == "),
+            ("Number", "0"),
+            ("", ")."),
+            ("TSMethod", "sum"),
+            ("", "()\n}\n\n"),
+            ("Macro", "println!"),
+            ("", "("),
+            ("String", "\"{}\""),
+            ("", ", "),
+            ("Function", "sum_even"),
+            ("", "(&["),
+            ("Number", "1"),
+            ("", ", "),
+            ("Number", "2"),
+            ("", "]));"),
+        ],
+    },
+    Sample {
+        language: "Python",
+        spans: &[
+            ("Keyword", "class"),
+            ("", " "),
+            ("Type", "Greeter"),
+            ("", ":\n    "),
+            ("String", "\"\"\"Greets people by name.\"\"\""),
+            ("", "\n\n    "),
+            ("Keyword", "def"),
+            ("", " "),
+            ("Function", "greet"),
+            ("", "("),
+            ("TSParameter", "self"),
+            ("", ", "),
+            ("TSParameter", "name"),
+            ("", "):\n        "),
+            ("Conditional", "if"),
+            ("", " "),
+            ("Keyword", "not"),
+            ("", " name:\n            "),
+            ("Keyword", "return"),
+            ("", " "),
+            ("Constant", "None"),
+            ("", "\n        "),
+            ("Comment", "# Formatted strings work too"),
+            ("", "\n        "),
+            ("Keyword", "return"),
+            ("", " "),
+            ("String", "f\"Hello, {name}!\""),
+        ],
+    },
+    Sample {
+        language: "Lua",
+        spans: &[
+            ("Keyword", "local"),
+            ("", " "),
+            ("Identifier", "M"),
+            ("Operator", " = "),
+            ("", "{}\n\n"),
+            ("Comment", "-- Counts the keys of a table"),
+            ("", "\n"),
+            ("Keyword", "function"),
+            ("", " M."),
+            ("Function", "count"),
+            ("", "("),
+            ("TSParameter", "tbl"),
+            ("", ")\n  "),
+            ("Keyword", "local"),
+            ("", " n"),
+            ("Operator", " = "),
+            ("Number", "0"),
+            ("", "\n  "),
+            ("Repeat", "for"),
+            ("", " _ "),
+            ("Repeat", "in"),
+            ("", " "),
+            ("TSFuncBuiltin", "pairs"),
+            ("", "(tbl) "),
+            ("Repeat", "do"),
+            ("", " n"),
+            ("Operator", " = "),
+            ("", "n"),
+            ("Operator", " + "),
+            ("Number", "1"),
+            ("", " "),
+            ("Repeat", "end"),
+            ("", "\n  "),
+            ("Keyword", "return"),
+            ("", " n\n"),
+            ("Keyword", "end"),
+        ],
+    },
+    Sample {
+        language: "JavaScript",
+        spans: &[
+            ("Keyword", "const"),
+            ("", " "),
+            ("Identifier", "answer"),
+            ("Operator", " = "),
+            ("Number", "42"),
+            ("", ";\n\n"),
+            ("Keyword", "async function"),
+            ("", " "),
+            ("Function", "fetchUser"),
+            ("", "("),
+            ("TSParameter", "id"),
+            ("", ") {\n  "),
+            ("Keyword", "const"),
+            ("", " "),
+            ("Identifier", "response"),
+            ("Operator", " = "),
+            ("Keyword", "await"),
+            ("", " "),
+            ("Function", "fetch"),
+            ("", "("),
+            ("String", "`/users/${id}`"),
+            ("", ");\n  "),
+            ("Conditional", "if"),
+            ("", " (!response."),
+            ("TSField", "ok"),
+            ("", ") "),
+            ("Keyword", "throw new"),
+            ("", " "),
+            ("Type", "Error"),
+            ("", "("),
+            ("String", "'Not found'"),
+            ("", ");\n  "),
+            ("Keyword", "return"),
+            ("", " response."),
+            ("TSMethod", "json"),
+            ("", "(); "),
+            ("Comment", "// parsed body"),
+            ("", "\n}"),
+        ],
+    },
+];
+
+/// The colors a span ends up rendered with
+#[derive(Debug, Clone, PartialEq)]
+pub struct Style {
+    pub foreground: String,
+    pub background: Option<String>,
+    pub bold: bool,
+    pub italic: bool,
+}
+
+/// Resolves the style of `group` the way Vim would, defaulting to `Normal`
+pub fn style(theme: &ResolvedTheme, group: &str) -> Style {
+    let normal = theme.normal_colors();
+    let highlight = if group.is_empty() {
+        None
+    } else {
+        theme.highlight(group)
+    };
+
+    let color = |name: &Option<String>| {
+        name.as_ref()
+            .and_then(|name| theme.color(name))
+            .map(str::to_string)
+    };
+
+    match highlight {
+        Some(highlight) => {
+            let has_style = |style: &str| {
+                highlight
+                    .text_style
+                    .as_deref()
+                    .is_some_and(|styles| styles.split(',').any(|s| s == style))
+            };
+            Style {
+                foreground: color(&highlight.foreground).unwrap_or(normal.0),
+                background: color(&highlight.background),
+                bold: has_style("bold"),
+                italic: has_style("italic"),
+            }
+        }
+        None => Style {
+            foreground: normal.0,
+            background: None,
+            bold: false,
+            italic: false,
+        },
+    }
+}
+
+pub fn render_html(theme: &ResolvedTheme) -> String {
+    let (foreground, background) = theme.normal_colors();
+    let title = escape(theme.metadata.name.as_deref().unwrap_or("djanho preview"));
+
+    let mut buffer = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n\
+         <style>\n\
+         body {{ background: {}; color: {}; font-family: sans-serif; }}\n\
+         pre {{ font-family: monospace; font-size: 14px; line-height: 1.4; }}\n\
+         </style>\n</head>\n<body>\n<h1>{}</h1>\n",
+        title, background, foreground, title
+    );
+
+    for sample in SAMPLES {
+        buffer += &format!("<h2>{}</h2>\n<pre>", sample.language);
+        for (group, text) in sample.spans {
+            let style = style(theme, group);
+            let mut css = format!("color: {};", style.foreground);
+            if let Some(background) = &style.background {
+                css += &format!(" background: {};", background);
+            }
+            if style.bold {
+                css += " font-weight: bold;";
+            }
+            if style.italic {
+                css += " font-style: italic;";
+            }
+            buffer += &format!("<span style=\"{}\">{}</span>", css, escape(text));
+        }
+        buffer += "</pre>\n";
+    }
+
+    buffer += "</body>\n</html>\n";
+    buffer
+}
+
+const FONT_SIZE: usize = 14;
+const LINE_HEIGHT: usize = 20;
+const CHAR_WIDTH: f32 = 8.4;
+const PADDING: usize = 20;
+
+pub fn render_svg(theme: &ResolvedTheme) -> String {
+    let (foreground, background) = theme.normal_colors();
+
+    // Split every sample into lines of spans, preceded by a heading line
+    let mut lines: Vec<Vec<(Style, &str)>> = Vec::new();
+    for sample in SAMPLES {
+        let heading = Style {
+            foreground: foreground.clone(),
+            background: None,
+            bold: true,
+            italic: false,
+        };
+        lines.push(vec![(heading, sample.language)]);
+
+        let mut line = Vec::new();
+        for (group, text) in sample.spans {
+            let style = style(theme, group);
+            let mut parts = text.split('\n').peekable();
+            while let Some(part) = parts.next() {
+                if !part.is_empty() {
+                    line.push((style.clone(), part));
+                }
+                if parts.peek().is_some() {
+                    lines.push(std::mem::take(&mut line));
+                }
+            }
+        }
+        lines.push(line);
+        lines.push(Vec::new());
+    }
+
+    let columns = lines
+        .iter()
+        .map(|line| line.iter().map(|(_, text)| text.chars().count()).sum())
+        .max()
+        .unwrap_or(0);
+    let width = (columns as f32 * CHAR_WIDTH) as usize + 2 * PADDING;
+    let height = lines.len() * LINE_HEIGHT + 2 * PADDING;
+
+    let mut buffer = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" \
+         font-family=\"monospace\" font-size=\"{}\">\n\
+         <rect width=\"100%\" height=\"100%\" fill=\"{}\"/>\n",
+        width, height, FONT_SIZE, background
+    );
+
+    for (idx, line) in lines.iter().enumerate() {
+        let y = PADDING + (idx + 1) * LINE_HEIGHT;
+        let mut column = 0;
+
+        for (style, text) in line {
+            let x = PADDING as f32 + column as f32 * CHAR_WIDTH;
+            let length = text.chars().count();
+            if let Some(background) = &style.background {
+                buffer += &format!(
+                    "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"/>\n",
+                    x,
+                    y + 5 - LINE_HEIGHT,
+                    length as f32 * CHAR_WIDTH,
+                    LINE_HEIGHT,
+                    background
+                );
+            }
+
+            let mut attributes = format!("fill=\"{}\"", style.foreground);
+            if style.bold {
+                attributes += " font-weight=\"bold\"";
+            }
+            if style.italic {
+                attributes += " font-style=\"italic\"";
+            }
+            buffer += &format!(
+                "<text x=\"{}\" y=\"{}\" {} xml:space=\"preserve\">{}</text>\n",
+                x,
+                y,
+                attributes,
+                escape(text)
+            );
+            column += length;
+        }
+    }
+
+    buffer += "</svg>\n";
+    buffer
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
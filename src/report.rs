@@ -0,0 +1,280 @@
+use std::fmt;
+
+use serde::Serialize;
+
+use crate::{
+    decoder::VSCodeScope,
+    highlights::{self, Source, BUILTIN_GROUPS},
+    ir::ResolvedTheme,
+    languages,
+    theme::Theme,
+};
+
+/// How much of a part of the theme, or of the groups djanho sets, is covered
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct Category {
+    pub name: &'static str,
+    pub covered: usize,
+    pub total: usize,
+}
+
+impl Category {
+    pub fn percentage(&self) -> f64 {
+        if self.total == 0 {
+            100.0
+        } else {
+            self.covered as f64 * 100.0 / self.total as f64
+        }
+    }
+}
+
+/// What a conversion used and left out of a theme
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct Report {
+    pub coverage: Vec<Category>,
+    /// `tokenColors` scopes that didn't contribute to any group
+    pub unused_scopes: Vec<String>,
+    /// `colors` keys that didn't contribute to any group
+    pub unused_colors: Vec<String>,
+    /// Groups djanho knows of that ended up neither colored nor linked
+    pub uncolored_groups: Vec<String>,
+    /// Colors that couldn't be parsed, and where they are in the theme
+    pub invalid_colors: Vec<String>,
+}
+
+impl Report {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("The report is always serializable")
+    }
+}
+
+/// Reports which scopes and color keys of `theme` were ignored, and which
+/// groups were left without colors.
+pub fn report(theme: &Theme) -> Report {
+    let resolved = theme.resolve();
+    let sources: Vec<&Source> = resolved
+        .highlights
+        .iter()
+        .flat_map(|highlight| &highlight.sources)
+        .collect();
+
+    let mut scopes: Vec<String> = Vec::new();
+    for token in &theme.vscode().tokens {
+        let token_scopes = match &token.scope {
+            Some(VSCodeScope::Single(scope)) => vec![scope.clone()],
+            Some(VSCodeScope::Multiple(scopes)) => scopes.clone(),
+            None => Vec::new(),
+        };
+        for scope in token_scopes {
+            if !scopes.contains(&scope) {
+                scopes.push(scope);
+            }
+        }
+    }
+    let mut color_keys: Vec<String> = theme
+        .vscode()
+        .colors
+        .as_ref()
+        .map(|colors| colors.keys().cloned().collect())
+        .unwrap_or_default();
+    color_keys.sort();
+
+    let (used_scopes, unused_scopes): (Vec<String>, Vec<String>) = scopes
+        .into_iter()
+        .partition(|scope| sources.contains(&&Source::Scope(scope.clone())));
+    let (used_colors, unused_colors): (Vec<String>, Vec<String>) = color_keys
+        .into_iter()
+        .partition(|key| sources.contains(&&Source::Color(key.clone())));
+
+    let syntax_groups = syntax_groups();
+    let builtins_set = count_set(&resolved, BUILTIN_GROUPS);
+    let syntax_set = count_set(&resolved, &syntax_groups);
+    let language_groups = language_groups();
+    let language_set = count_set(&resolved, &language_groups);
+
+    let mut uncolored_groups: Vec<String> = Vec::new();
+    for group in known_groups() {
+        if !is_set(&resolved, group) {
+            uncolored_groups.push(group.to_string());
+        }
+    }
+
+    Report {
+        coverage: vec![
+            Category {
+                name: "tokenColors scopes",
+                covered: used_scopes.len(),
+                total: used_scopes.len() + unused_scopes.len(),
+            },
+            Category {
+                name: "colors keys",
+                covered: used_colors.len(),
+                total: used_colors.len() + unused_colors.len(),
+            },
+            Category {
+                name: "built-in groups",
+                covered: builtins_set,
+                total: BUILTIN_GROUPS.len(),
+            },
+            Category {
+                name: "syntax groups",
+                covered: syntax_set,
+                total: syntax_groups.len(),
+            },
+            Category {
+                name: "language groups",
+                covered: language_set,
+                total: language_groups.len(),
+            },
+        ],
+        unused_scopes,
+        unused_colors,
+        uncolored_groups,
+        invalid_colors: resolved
+            .invalid_colors
+            .iter()
+            .map(|invalid| invalid.to_string())
+            .collect(),
+    }
+}
+
+/// A group counts as set when it is linked or has any attribute
+fn is_set(theme: &ResolvedTheme, group: &str) -> bool {
+    theme.links.iter().any(|link| link.group == group)
+        || theme
+            .highlights
+            .iter()
+            .any(|highlight| highlight.group == group && highlight.has_attributes())
+}
+
+fn count_set(theme: &ResolvedTheme, groups: &[&str]) -> usize {
+    groups.iter().filter(|group| is_set(theme, group)).count()
+}
+
+/// The groups `tokenColors` scopes are mapped to
+fn syntax_groups() -> Vec<&'static str> {
+    let mut groups = Vec::new();
+    for (_, group, _) in highlights::highlights().tokens {
+        if !groups.contains(&group) {
+            groups.push(group);
+        }
+    }
+    groups
+}
+
+/// The groups of every language pack
+fn language_groups() -> Vec<&'static str> {
+    let mut groups = Vec::new();
+    for language in languages::languages() {
+        for (_, group) in language.tokens {
+            if !groups.contains(&group) {
+                groups.push(group);
+            }
+        }
+    }
+    groups
+}
+
+/// Every group djanho may set, built-in groups first
+fn known_groups() -> Vec<&'static str> {
+    let highlights = highlights::highlights();
+    let mut groups: Vec<&'static str> = BUILTIN_GROUPS.to_vec();
+    let others = syntax_groups()
+        .into_iter()
+        .chain(language_groups())
+        .chain(highlights.colors.iter().map(|color| color.0))
+        .chain(highlights.undercurls.iter().map(|undercurl| undercurl.0));
+    for group in others {
+        if !groups.contains(&group) {
+            groups.push(group);
+        }
+    }
+    groups
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Coverage:")?;
+        for category in &self.coverage {
+            writeln!(
+                f,
+                "  {:<20} {:>4}/{:<4} {:>5.1}%",
+                category.name,
+                category.covered,
+                category.total,
+                category.percentage()
+            )?;
+        }
+
+        let lists = [
+            ("Unused tokenColors scopes", &self.unused_scopes),
+            ("Unused colors keys", &self.unused_colors),
+            ("Groups without colors", &self.uncolored_groups),
+            ("Invalid colors", &self.invalid_colors),
+        ];
+        for (title, items) in lists {
+            if items.is_empty() {
+                continue;
+            }
+            writeln!(f, "\n{} ({}):", title, items.len())?;
+            for item in items {
+                writeln!(f, "  {}", item)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_report_unused_parts_of_a_theme() {
+        let theme: Theme = r##"{
+            "colors": {
+                "editor.background": "#1e1e2e",
+                "diffEditor.insertedTextBackground": "#a6e3a133",
+                "notebook.cellBorderColor": "#313244"
+            },
+            "tokenColors": [
+                { "scope": ["comment", "source.rust comment"], "settings": { "foreground": "#6c7086" } }
+            ]
+        }"##
+        .parse()
+        .unwrap();
+        let report = report(&theme);
+
+        assert_eq!(report.unused_scopes, vec!["source.rust comment"]);
+        // Colors djanho derives other colors from count as used
+        assert_eq!(report.unused_colors, vec!["notebook.cellBorderColor"]);
+        assert!(report.uncolored_groups.contains(&"NormalNC".to_string()));
+        assert!(!report.uncolored_groups.contains(&"Comment".to_string()));
+        assert_eq!(report.coverage[0].covered, 1);
+    }
+
+    #[test]
+    fn can_report_unset_builtins() {
+        let theme: Theme =
+            r##"{ "colors": { "editor.background": "#1e1e2e", "editor.foreground": "#12345" } }"##
+                .parse()
+                .unwrap();
+        let report = report(&theme);
+
+        let builtins = &report.coverage[2];
+        let unset: Vec<&String> = report
+            .uncolored_groups
+            .iter()
+            .filter(|group| BUILTIN_GROUPS.contains(&group.as_str()))
+            .collect();
+        assert!(!unset.contains(&&"NormalFloat".to_string()));
+        assert!(!unset.contains(&&"VisualNOS".to_string()));
+        assert!(unset.contains(&&"NormalNC".to_string()));
+        assert_eq!(builtins.total, BUILTIN_GROUPS.len());
+        assert_eq!(builtins.covered + unset.len(), BUILTIN_GROUPS.len());
+        assert_eq!(
+            report.invalid_colors,
+            vec![r#"colors["editor.foreground"]: Invalid hex color: #12345"#]
+        );
+    }
+}
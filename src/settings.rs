@@ -0,0 +1,126 @@
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+
+use json_comments::StripComments;
+use serde::Deserialize;
+
+use crate::{
+    decoder::VSCodeTheme,
+    error::{Error, Result},
+    patch::Patch,
+};
+
+/// The parts of a VSCode user `settings.json` that change how themes look
+#[derive(Deserialize, Debug, Default, Clone)]
+pub struct Settings {
+    /// Name of the active theme
+    #[serde(rename = "workbench.colorTheme")]
+    pub color_theme: Option<String>,
+    /// The color and token color customizations
+    #[serde(flatten)]
+    pub customizations: Patch,
+}
+
+impl Settings {
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Settings> {
+        let path = path.as_ref();
+        Settings::from_json(&fs::read_to_string(path).map_err(Error::io(path))?)
+    }
+
+    /// Decodes the settings, allowing the comments and trailing commas
+    /// VSCode allows in them
+    pub fn from_json(body: &str) -> Result<Settings> {
+        let mut stripped = String::new();
+        StripComments::new(body.as_bytes()).read_to_string(&mut stripped)?;
+        serde_json::from_str(&strip_trailing_commas(&stripped)).map_err(Error::json("settings"))
+    }
+
+    /// Applies the customizations on top of `theme`. The `[Theme Name]`
+    /// sections are matched against the name of the theme, or against the
+    /// active theme when it has none.
+    pub fn apply(&self, theme: &mut VSCodeTheme) -> Result<()> {
+        let name = theme.name.clone().or_else(|| self.color_theme.clone());
+        self.customizations.apply_as(theme, name.as_deref())
+    }
+}
+
+fn strip_trailing_commas(body: &str) -> String {
+    let chars: Vec<char> = body.chars().collect();
+    let mut result = String::with_capacity(body.len());
+    let mut in_string = false;
+    let mut escaped = false;
+
+    for (idx, &c) in chars.iter().enumerate() {
+        if in_string {
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == '"' {
+                in_string = false;
+            }
+        } else if c == '"' {
+            in_string = true;
+        } else if c == ',' {
+            let next = chars[idx + 1..].iter().find(|c| !c.is_whitespace());
+            if let Some('}') | Some(']') = next {
+                continue;
+            }
+        }
+        result.push(c);
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{decoder, highlights};
+
+    #[test]
+    fn can_apply_user_settings() {
+        let settings = Settings::from_json(
+            r##"{
+                // Trailing commas are fine in settings.json
+                "editor.fontSize": 14,
+                "workbench.colorTheme": "Nord",
+                "workbench.colorCustomizations": {
+                    "[Nord]": { "editor.background": "#2e3440", },
+                },
+                "editor.tokenColorCustomizations": {
+                    "semanticTokenColors": { "*.mutable": { "underline": true } },
+                },
+                "editor.semanticTokenColorCustomizations": {
+                    "rules": { "variable.readonly:rust": "#d8dee9", "parameter": "#e5e9f0", },
+                },
+            }"##,
+        )
+        .unwrap();
+        let mut theme = decoder::parse_reader(r#"{ "tokenColors": [] }"#.as_bytes()).unwrap();
+        settings.apply(&mut theme).unwrap();
+
+        assert_eq!(theme.colors.unwrap()["editor.background"], "#2e3440");
+        let groups: Vec<String> = theme
+            .semantic_tokens
+            .keys()
+            .filter_map(|selector| highlights::semantic_group(selector))
+            .collect();
+        assert_eq!(
+            groups,
+            vec![
+                "@lsp.mod.mutable",
+                "@lsp.type.parameter",
+                "@lsp.typemod.variable.readonly.rust"
+            ]
+        );
+        assert_eq!(
+            theme.semantic_tokens["*.mutable"]
+                .settings()
+                .font_style
+                .as_deref(),
+            Some("underline")
+        );
+    }
+}
@@ -0,0 +1,66 @@
+use crate::{
+    colors::{self, OKLCH},
+    ir::ResolvedTheme,
+};
+
+/// Whether the theme's `Normal` background is light
+pub fn is_light(theme: &ResolvedTheme) -> bool {
+    let (_, background) = theme.normal_colors();
+    colors::from_hex_string(&background)
+        .map(colors::is_light)
+        .unwrap_or(false)
+}
+
+/// OKLCH lightness of the background of synthesized light themes
+const LIGHT_BACKGROUND: f64 = 0.97;
+/// OKLCH lightness of the background of synthesized dark themes
+const DARK_BACKGROUND: f64 = 0.22;
+
+/// Synthesizes a light variant of a dark theme, or a dark variant of a light
+/// one, by inverting the OKLCH lightness of its palette around the background.
+/// Hue and chroma are kept, and so is the lightness difference between every
+/// color and the background, which keeps their relative contrast.
+pub fn invert_variant(theme: &ResolvedTheme) -> ResolvedTheme {
+    let was_light = is_light(theme);
+    let mut inverted = theme.clone();
+
+    let (_, background) = theme.normal_colors();
+    let background = colors::from_hex_string(&background)
+        .map(|color| colors::to_oklch(color).l)
+        .unwrap_or(0.0);
+    let target = if was_light {
+        DARK_BACKGROUND
+    } else {
+        LIGHT_BACKGROUND
+    };
+    // Mirrors lightness so `background` ends up at `target`
+    let invert = |hex: &str| invert_lightness(hex, |l| target + background - l);
+
+    for entry in &mut inverted.palette {
+        entry.color = invert(&entry.color);
+    }
+    inverted.metadata.background = invert(&theme.metadata.background);
+
+    let (from, to) = if was_light {
+        ("light", "dark")
+    } else {
+        ("dark", "light")
+    };
+    inverted.metadata.kind = Some(to.to_string());
+    inverted.metadata.synthesized_from = Some(from.to_string());
+
+    inverted
+}
+
+fn invert_lightness<F: Fn(f64) -> f64>(hex: &str, invert: F) -> String {
+    match colors::from_hex_string(hex) {
+        Ok(color) => {
+            let oklch = colors::to_oklch(color);
+            colors::to_rgb_hex_string(colors::from_oklch(OKLCH {
+                l: invert(oklch.l).clamp(0.0, 1.0),
+                ..oklch
+            }))
+        }
+        Err(_) => hex.to_string(),
+    }
+}
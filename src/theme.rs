@@ -0,0 +1,80 @@
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::{
+    decoder::{self, VSCodeTheme},
+    error::{Error, Result},
+    generators,
+    ir::ResolvedTheme,
+    patch::Patch,
+    settings::Settings,
+};
+
+/// A decoded VSCode theme, ready to be converted
+#[derive(Debug)]
+pub struct Theme {
+    theme: VSCodeTheme,
+    sources: Vec<PathBuf>,
+}
+
+impl Theme {
+    /// Reads and decodes the theme at `path`
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Theme> {
+        let mut sources = Vec::new();
+        let theme = decoder::parse_file_with_includes(path.as_ref(), &mut sources)?;
+        Ok(Theme { theme, sources })
+    }
+
+    /// Decodes a theme from any reader, such as a file or stdin
+    pub fn from_reader<R: Read>(reader: R) -> Result<Theme> {
+        decoder::parse_reader(reader).map(Theme::from)
+    }
+
+    /// The `name` field of the theme, if it has one
+    pub fn name(&self) -> Option<&str> {
+        self.theme.name.as_deref()
+    }
+
+    /// Every file the theme was read from, including the `include`d ones
+    pub fn sources(&self) -> &[PathBuf] {
+        &self.sources
+    }
+
+    /// The raw VSCode theme, as it was decoded
+    pub fn vscode(&self) -> &VSCodeTheme {
+        &self.theme
+    }
+
+    /// Merges `patch` into the theme, before it gets resolved
+    pub fn apply_patch(&mut self, patch: &Patch) -> Result<()> {
+        patch.apply(&mut self.theme)
+    }
+
+    /// Applies the customizations of a VSCode `settings.json` to the theme
+    pub fn apply_settings(&mut self, settings: &Settings) -> Result<()> {
+        settings.apply(&mut self.theme)
+    }
+
+    /// Resolves the theme into the representation the generators render from
+    pub fn resolve(&self) -> ResolvedTheme {
+        generators::resolve(&self.theme)
+    }
+}
+
+impl FromStr for Theme {
+    type Err = Error;
+
+    fn from_str(body: &str) -> Result<Theme> {
+        Theme::from_reader(body.as_bytes())
+    }
+}
+
+impl From<VSCodeTheme> for Theme {
+    fn from(theme: VSCodeTheme) -> Theme {
+        Theme {
+            theme,
+            sources: Vec::new(),
+        }
+    }
+}
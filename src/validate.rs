@@ -0,0 +1,281 @@
+use std::collections::HashSet;
+
+use mlua::Lua;
+
+use crate::{
+    convert::OutputFile,
+    error::{Error, Result},
+    highlights::is_group_name,
+};
+
+/// Attributes `:highlight` accepts in `gui=`
+const ATTRIBUTES: &[&str] = &[
+    "bold",
+    "italic",
+    "underline",
+    "undercurl",
+    "underdouble",
+    "underdotted",
+    "underdashed",
+    "strikethrough",
+    "reverse",
+    "inverse",
+    "standout",
+    "nocombine",
+    "NONE",
+];
+
+/// Checks that a generated file would load, according to its extension
+pub fn validate(file: &OutputFile) -> Result<()> {
+    let result = match file
+        .path
+        .extension()
+        .and_then(|extension| extension.to_str())
+    {
+        Some("vim") => vimscript(&file.contents),
+        Some("lua") => lua(&file.contents),
+        _ => Ok(()),
+    };
+    result.map_err(|message| Error::InvalidOutput {
+        path: file.path.clone(),
+        message,
+    })
+}
+
+/// Compiles the Lua code with LuaJIT, the interpreter Neovim embeds, without
+/// running it
+pub fn lua(source: &str) -> std::result::Result<(), String> {
+    Lua::new()
+        .load(source)
+        .set_name("=output")
+        .into_function()
+        .map(|_| ())
+        .map_err(|err| err.to_string())
+}
+
+/// Checks the Vimscript against the subset of the language colorschemes are
+/// generated in. The `:highlight` calls, links and palette variables are
+/// parsed, and any other line must have balanced strings and brackets.
+pub fn vimscript(source: &str) -> std::result::Result<(), String> {
+    let mut blocks: Vec<&str> = Vec::new();
+    let mut variables: HashSet<String> = HashSet::new();
+
+    for (idx, line) in source.lines().enumerate() {
+        check_line(line.trim(), &mut blocks, &mut variables)
+            .map_err(|message| format!("line {}: {}", idx + 1, message))?;
+    }
+
+    match blocks.last() {
+        Some(block) => Err(format!("unterminated {}", block)),
+        None => Ok(()),
+    }
+}
+
+fn check_line<'a>(
+    line: &'a str,
+    blocks: &mut Vec<&'a str>,
+    variables: &mut HashSet<String>,
+) -> std::result::Result<(), String> {
+    if line.is_empty() || line.starts_with('"') {
+        return Ok(());
+    }
+
+    let keyword = line.split_whitespace().next().unwrap_or_default();
+    match keyword {
+        "function" | "function!" | "if" | "for" => blocks.push(keyword.trim_end_matches('!')),
+        "else" | "elseif" if blocks.last() != Some(&"if") => {
+            return Err(format!("{} outside of an if", keyword))
+        }
+        "endfunction" | "endif" | "endfor" => {
+            let block = blocks.pop();
+            if block.map(|block| format!("end{}", block)).as_deref() != Some(keyword) {
+                return Err(format!("unexpected {}", keyword));
+            }
+        }
+        _ => {}
+    }
+
+    // Function bodies aren't generated from the theme
+    if blocks.contains(&"function") && keyword != "function" {
+        return tokens(line).map(|_| ());
+    }
+
+    if let Some(rest) = line.strip_prefix("let s:") {
+        let (name, value) = rest
+            .split_once(" = ")
+            .ok_or("expected a variable assignment")?;
+        let value = string(value)?;
+        let is_hex = value.len() == 7
+            && value.starts_with('#')
+            && value[1..].chars().all(|c| c.is_ascii_hexdigit());
+        if !is_hex && value != "NONE" {
+            return Err(format!("invalid color {}", value));
+        }
+        variables.insert(name.to_string());
+    } else if let Some(rest) = line.strip_prefix("call s:highlight(") {
+        let args = rest
+            .strip_suffix(')')
+            .ok_or("expected the arguments to end the line")?;
+        highlight(&split_args(args)?, variables)?;
+    } else if let Some(rest) = line.strip_prefix("highlight! link ") {
+        let groups: Vec<&str> = rest.split_whitespace().collect();
+        if groups.len() != 2 || !groups.iter().all(|group| is_group_name(group)) {
+            return Err(format!("invalid link {}", rest));
+        }
+    } else {
+        tokens(line)?;
+    }
+
+    Ok(())
+}
+
+/// Checks the arguments of `s:highlight(group, bg, fg, style[, sp])`
+fn highlight(args: &[&str], variables: &HashSet<String>) -> std::result::Result<(), String> {
+    if args.len() != 4 && args.len() != 5 {
+        return Err(format!("expected 4 or 5 arguments, got {}", args.len()));
+    }
+
+    let group = string(args[0])?;
+    if !is_group_name(&group) {
+        return Err(format!("invalid group name {}", group));
+    }
+
+    for (idx, color) in args.iter().enumerate().skip(1) {
+        if idx == 3 {
+            continue;
+        }
+        let defined = color
+            .strip_prefix("s:")
+            .is_some_and(|name| variables.contains(name));
+        let empty = idx < 3 && *color == "''";
+        if !defined && !empty {
+            return Err(format!("undefined color {}", color));
+        }
+    }
+
+    let style = string(args[3])?;
+    for attribute in style.split(',').filter(|attribute| !attribute.is_empty()) {
+        if !ATTRIBUTES.contains(&attribute) {
+            return Err(format!("invalid attribute {}", attribute));
+        }
+    }
+
+    Ok(())
+}
+
+/// Decodes a single quoted string literal
+fn string(literal: &str) -> std::result::Result<String, String> {
+    let inner = literal
+        .strip_prefix('\'')
+        .and_then(|literal| literal.strip_suffix('\''))
+        .filter(|inner| !inner.replace("''", "").contains('\''))
+        .ok_or_else(|| format!("expected a string, got {}", literal))?;
+    Ok(inner.replace("''", "'"))
+}
+
+/// Splits arguments on the commas outside of strings
+fn split_args(args: &str) -> std::result::Result<Vec<&str>, String> {
+    let mut result = Vec::new();
+    let mut start = 0;
+    for (idx, token) in tokens(args)? {
+        if token == ',' {
+            result.push(args[start..idx].trim());
+            start = idx + 1;
+        }
+    }
+    result.push(args[start..].trim());
+    Ok(result)
+}
+
+/// Returns the position of the characters outside of strings, checking that
+/// strings are terminated and brackets balanced
+fn tokens(line: &str) -> std::result::Result<Vec<(usize, char)>, String> {
+    let mut result = Vec::new();
+    let mut brackets: Vec<char> = Vec::new();
+    let mut chars = line.char_indices().peekable();
+
+    while let Some((idx, c)) = chars.next() {
+        match c {
+            '\'' => loop {
+                match chars.next() {
+                    Some((_, '\'')) if chars.peek().map(|(_, c)| *c) == Some('\'') => {
+                        chars.next();
+                    }
+                    Some((_, '\'')) => break,
+                    Some(_) => {}
+                    None => return Err("unterminated string".to_string()),
+                }
+            },
+            '"' => loop {
+                match chars.next() {
+                    Some((_, '\\')) => {
+                        chars.next();
+                    }
+                    Some((_, '"')) => break,
+                    Some(_) => {}
+                    None => return Err("unterminated string".to_string()),
+                }
+            },
+            '(' | '[' | '{' => brackets.push(c),
+            ')' | ']' | '}' => {
+                let expected = match c {
+                    ')' => '(',
+                    ']' => '[',
+                    _ => '{',
+                };
+                if brackets.pop() != Some(expected) {
+                    return Err(format!("unbalanced {}", c));
+                }
+            }
+            _ => result.push((idx, c)),
+        }
+    }
+
+    match brackets.last() {
+        Some(bracket) => Err(format!("unbalanced {}", bracket)),
+        None => Ok(result),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{convert_with, Options, Target, Theme};
+
+    #[test]
+    fn can_validate_generated_output() {
+        let theme: Theme = r##"{
+            "colors": { "editor.background": "#282a36" },
+            "tokenColors": [
+                { "scope": "comment", "settings": { "foreground": "#6272a4", "fontStyle": "italic" } }
+            ],
+            "semanticTokenColors": { "variable": "#f8f8f2", "it's": "#ff5555" }
+        }"##
+        .parse()
+        .unwrap();
+
+        // Quotes in the name are escaped, and groups that would be invalid
+        // are left out, so every kind of output loads
+        let builders = [
+            Options::builder(),
+            Options::builder().compiled(true),
+            Options::builder().lua_module(true),
+            Options::builder().namespace(true),
+        ];
+        for builder in builders.iter() {
+            let options = builder.clone().name("it's \"quoted\"").build();
+            for target in [Target::Vimscript, Target::Lua].iter() {
+                convert_with(&theme, *target, &options).unwrap();
+            }
+        }
+
+        assert!(vimscript("call s:highlight('Comment, '', '', '')").is_err());
+        assert!(vimscript("call s:highlight('Comment', s:Color0, '', '')").is_err());
+        assert!(vimscript("highlight! link Comment it's").is_err());
+        assert!(vimscript("if has('nvim')\n").is_err());
+        assert!(lua("highlight('Comment', nil, Color0, 'italic'").is_err());
+        // Lua 5.4 syntax that Neovim doesn't understand
+        assert!(lua("local ns <const> = 0").is_err());
+        assert!(lua("return 7 // 2").is_err());
+    }
+}
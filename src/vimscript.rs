@@ -1,64 +1,166 @@
 use indoc::indoc;
 
-use crate::highlights::map_font_styles;
-use crate::{generator::ConfigGenerator, highlights::VimHighlight};
+use crate::{
+    generator::{indent, ConfigGenerator},
+    highlights::VimHighlight,
+    ir::ResolvedTheme,
+};
 
+const HEADER: &str = indoc! {"
+    \" Colorscheme generated by https://github.com/arcticlimer/djanho
+    highlight clear
+    let g:colors_name = {name}
+
+    function s:highlight(group, bg, fg, style, ...)
+      let gui = a:style == '' ? '' : 'gui=' . a:style
+      let fg = a:fg == '' ? '' : 'guifg=' . a:fg
+      let bg = a:bg == '' ? '' : 'guibg=' . a:bg
+      let sp = a:0 == 0 ? '' : 'guisp=' . a:1
+      exec 'hi ' . a:group . ' ' . bg . ' ' . fg  . ' ' . gui . ' ' . sp
+    endfunction
+
+    function s:override(group, attrs, palette)
+      if has_key(a:attrs, 'link')
+        exec 'highlight! link ' . a:group . ' ' . a:attrs.link
+        return
+      endif
+      let colors = map(filter(copy(a:attrs), 'v:key !=# \"style\"'), 'get(a:palette, v:val, v:val)')
+      let args = [a:group, get(colors, 'bg', ''), get(colors, 'fg', ''), get(a:attrs, 'style', '')]
+      call call('s:highlight', has_key(colors, 'sp') ? args + [colors.sp] : args)
+    endfunction\n"
+};
+
+const OVERRIDES: &str = indoc! {"
+
+    \" Local tweaks that survive regenerating the colorscheme go in
+    \" g:{variable}_overrides, where colors are hex or names of the palette:
+    \"   let g:{variable}_overrides = {'Comment': {'fg': 'Color3', 'style': 'NONE'}}
+    let g:{variable}_palette = {{palette}}
+    for [s:group, s:attrs] in items(get(g:, '{variable}_overrides', {}))
+      call s:override(s:group, s:attrs, g:{variable}_palette)
+    endfor\n"
+};
+
+#[derive(Debug)]
 pub struct VimscriptGenerator {
-    buffer: String,
+    /// Name of the colorscheme, which the user overrides are keyed by
+    pub name: String,
 }
 
-impl Default for VimscriptGenerator {
-    fn default() -> VimscriptGenerator {
-        let mut _self = VimscriptGenerator {
-            buffer: String::new(),
-        };
-        _self.buffer += indoc! {
-          "
-            \" Colorscheme generated by https://github.com/arcticlimer/djanho
-            highlight clear
-
-            function s:highlight(group, bg, fg, style)
-              let gui = a:style == '' ? '' : 'gui=' . a:style
-              let fg = a:fg == '' ? '' : 'guifg=' . a:fg
-              let bg = a:bg == '' ? '' : 'guibg=' . a:bg
-              exec 'hi ' . a:group . ' ' . bg . ' ' . fg  . ' ' . gui
-            endfunction
-          "
-        };
-        _self
+impl VimscriptGenerator {
+    pub fn new<S: Into<String>>(name: S) -> VimscriptGenerator {
+        VimscriptGenerator { name: name.into() }
+    }
+
+    fn header(&self) -> String {
+        HEADER.replace("{name}", &quote(&self.name))
+    }
+
+    fn overrides(&self, theme: &ResolvedTheme) -> String {
+        let palette: Vec<String> = theme
+            .palette
+            .iter()
+            .map(|color| format!("{}: s:{}", quote(&color.name), color.name))
+            .collect();
+        OVERRIDES
+            .replace("{variable}", &variable_name(&self.name))
+            .replace("{palette}", &palette.join(", "))
     }
 }
 
 impl ConfigGenerator for VimscriptGenerator {
-    fn collect(&self) -> String {
-        self.buffer.to_string()
+    fn render(&self, theme: &ResolvedTheme) -> String {
+        self.header() + &body(theme) + &self.overrides(theme)
+    }
+
+    fn render_pair(&self, dark: &ResolvedTheme, light: &ResolvedTheme) -> String {
+        let mut buffer = self.header();
+        buffer += "\nif &background ==# 'light'\n";
+        buffer += &indent(body(light).trim_start(), 2);
+        buffer += "else\n";
+        buffer += &indent(body(dark).trim_start(), 2);
+        buffer += "endif\n";
+        // Both variants share the names of their palette
+        buffer += &self.overrides(dark);
+        buffer
+    }
+}
+
+/// Turns the name of the colorscheme into a valid variable name
+fn variable_name(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect()
+}
+
+fn body(theme: &ResolvedTheme) -> String {
+    let mut buffer = String::new();
+
+    if let Some(note) = theme.metadata.synthesized_note() {
+        buffer += &format!("\n\" {}\n", note);
     }
-    fn link(&mut self, group: &str, target: &str) {
-        self.buffer += link(group, target).as_str()
+
+    buffer += "\n";
+    for color in &theme.palette {
+        buffer += &create_variable(&color.name, &color.color)
     }
-    fn highlight(&mut self, options: &VimHighlight) {
-        self.buffer += highlight(options).as_str()
+
+    // Treesitter captures such as `@diff.plus` are only valid group names in
+    // Neovim, so they are kept apart for Vim
+    let mut neovim_only = String::new();
+
+    buffer += "\n";
+    for options in &theme.highlights {
+        if is_neovim_only(&options.group) {
+            neovim_only += &highlight(options)
+        } else {
+            buffer += &highlight(options)
+        }
     }
-    fn variable(&mut self, name: String, color: String) {
-        self.buffer += create_variable(name, color).as_str()
+
+    buffer += "\n";
+    for vim_link in &theme.links {
+        if is_neovim_only(&vim_link.group) || is_neovim_only(&vim_link.target) {
+            neovim_only += &link(&vim_link.group, &vim_link.target)
+        } else {
+            buffer += &link(&vim_link.group, &vim_link.target)
+        }
     }
-    fn newline(&mut self) {
-        self.buffer += "\n";
+
+    if !neovim_only.is_empty() {
+        buffer += "\nif has('nvim')\n";
+        buffer += &indent(&neovim_only, 2);
+        buffer += "endif\n";
     }
+
+    buffer
+}
+
+fn is_neovim_only(group: &str) -> bool {
+    group.starts_with('@')
 }
 
 fn highlight(options: &VimHighlight) -> String {
     let guibg = helper(&options.background);
     let guifg = helper(&options.foreground);
-    let gui = map_font_styles(&options.text_style).unwrap_or_default();
+    let gui = options.text_style.clone().unwrap_or_default();
 
     if guibg == "''" && guifg == "''" && gui.is_empty() {
         return String::new();
     }
 
+    let guisp = match &options.special {
+        Some(special) => format!(", s:{}", special),
+        None => String::new(),
+    };
+
     format!(
-        "call s:highlight('{}', {}, {}, '{}')\n",
-        options.group, guibg, guifg, gui
+        "call s:highlight({}, {}, {}, {}{})\n",
+        quote(&options.group),
+        guibg,
+        guifg,
+        quote(&gui),
+        guisp
     )
 }
 
@@ -74,6 +176,11 @@ fn link(group: &str, target: &str) -> String {
     format!("highlight! link {} {}\n", group, target)
 }
 
-fn create_variable(name: String, color: String) -> String {
-    format!("let s:{} = '{}'\n", name, color)
+fn create_variable(name: &str, color: &str) -> String {
+    format!("let s:{} = {}\n", name, quote(color))
+}
+
+/// Quotes `value` as a single quoted string, where quotes are doubled
+pub fn quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
 }
@@ -0,0 +1,215 @@
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+/// How often the watched files are polled for changes
+pub const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Calls `regenerate` every time one of the files it returned last time
+/// changes. `regenerate` is also called once before watching starts.
+///
+/// When regenerating fails, `regenerate` returns the files it knows about,
+/// which are watched along with the previous ones.
+pub fn watch<F>(mut regenerate: F) -> !
+where
+    F: FnMut() -> Result<Vec<PathBuf>, Vec<PathBuf>>,
+{
+    let mut watcher = Watcher::new(&mut regenerate);
+    loop {
+        thread::sleep(POLL_INTERVAL);
+        watcher.poll(&mut regenerate);
+    }
+}
+
+struct Watcher {
+    files: Vec<PathBuf>,
+    last_modified: Vec<Option<SystemTime>>,
+}
+
+impl Watcher {
+    fn new<F>(regenerate: &mut F) -> Self
+    where
+        F: FnMut() -> Result<Vec<PathBuf>, Vec<PathBuf>>,
+    {
+        let mut watcher = Watcher {
+            files: Vec::new(),
+            last_modified: Vec::new(),
+        };
+        watcher.regenerate(regenerate);
+        watcher
+    }
+
+    /// Regenerates if a watched file changed since the last poll, returning
+    /// whether it did
+    fn poll<F>(&mut self, regenerate: &mut F) -> bool
+    where
+        F: FnMut() -> Result<Vec<PathBuf>, Vec<PathBuf>>,
+    {
+        if modification_times(&self.files) == self.last_modified {
+            return false;
+        }
+        self.regenerate(regenerate);
+        true
+    }
+
+    fn regenerate<F>(&mut self, regenerate: &mut F)
+    where
+        F: FnMut() -> Result<Vec<PathBuf>, Vec<PathBuf>>,
+    {
+        match regenerate() {
+            Ok(files) => self.files = files,
+            // A theme that couldn't be read may still include the files it
+            // did before, so those are still watched
+            Err(files) => {
+                for file in files {
+                    if !self.files.contains(&file) {
+                        self.files.push(file);
+                    }
+                }
+            }
+        }
+        self.last_modified = modification_times(&self.files);
+    }
+}
+
+fn modification_times(files: &[PathBuf]) -> Vec<Option<SystemTime>> {
+    files
+        .iter()
+        .map(|file| fs::metadata(file).and_then(|meta| meta.modified()).ok())
+        .collect()
+}
+
+/// Tells the editor listening on `target` to source `colorscheme`.
+///
+/// `target` can either be the socket of a `nvim --listen` instance or a named
+/// pipe, in which case the command is written as a line of text. Writing to a
+/// named pipe blocks until something opens it for reading.
+pub fn notify(target: &Path, colorscheme: &Path) -> io::Result<()> {
+    let colorscheme = colorscheme.canonicalize()?;
+    let command = format!("source {}", escape_path(&colorscheme));
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::{fs::FileTypeExt, net::UnixStream};
+
+        if fs::metadata(target)?.file_type().is_socket() {
+            let mut stream = UnixStream::connect(target)?;
+            return stream.write_all(&rpc_notification("nvim_command", &command));
+        }
+    }
+
+    let mut pipe = OpenOptions::new().write(true).open(target)?;
+    writeln!(pipe, "{}", command)
+}
+
+/// Characters `fnameescape()` escapes in a file name
+#[cfg(unix)]
+const SPECIAL_CHARACTERS: &str = " \t\n*?[{`$\\%#'\"|!<";
+#[cfg(not(unix))]
+const SPECIAL_CHARACTERS: &str = " \t\n*?[{`$%#'\"|!<";
+
+/// Escapes `path` to be used as a file name in an Ex command, as
+/// `fnameescape()` does
+fn escape_path(path: &Path) -> String {
+    let path = path.display().to_string();
+    let mut escaped = String::with_capacity(path.len());
+    for (i, c) in path.chars().enumerate() {
+        if SPECIAL_CHARACTERS.contains(c) || (i == 0 && (c == '+' || c == '>')) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    if escaped == "-" {
+        escaped.insert(0, '\\');
+    }
+    escaped
+}
+
+/// Encodes a msgpack-rpc notification, `[2, method, [argument]]`
+fn rpc_notification(method: &str, argument: &str) -> Vec<u8> {
+    let mut message = vec![0x93, 0x02];
+    write_msgpack_str(&mut message, method);
+    message.push(0x91);
+    write_msgpack_str(&mut message, argument);
+    message
+}
+
+fn write_msgpack_str(buffer: &mut Vec<u8>, value: &str) {
+    let len = value.len();
+    if len < 32 {
+        buffer.push(0xa0 | len as u8);
+    } else if len <= u8::MAX as usize {
+        buffer.push(0xd9);
+        buffer.push(len as u8);
+    } else if len <= u16::MAX as usize {
+        buffer.push(0xda);
+        buffer.extend_from_slice(&(len as u16).to_be_bytes());
+    } else {
+        buffer.push(0xdb);
+        buffer.extend_from_slice(&(len as u32).to_be_bytes());
+    }
+    buffer.extend_from_slice(value.as_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_encode_rpc_notifications() {
+        let message = rpc_notification("nvim_command", "source a.vim");
+        let mut target = vec![0x93, 0x02, 0xac];
+        target.extend_from_slice(b"nvim_command");
+        target.extend_from_slice(&[0x91, 0xac]);
+        target.extend_from_slice(b"source a.vim");
+
+        assert_eq!(message, target)
+    }
+
+    #[test]
+    fn can_escape_paths() {
+        let path = Path::new("/tmp/my theme #1 (50%)|x.vim");
+        assert_eq!(
+            escape_path(path),
+            "/tmp/my\\ theme\\ \\#1\\ (50\\%)\\|x.vim"
+        );
+        assert_eq!(escape_path(Path::new("+x")), "\\+x");
+    }
+
+    #[test]
+    fn can_keep_watching_includes_after_a_failure() {
+        let dir = std::env::temp_dir().join(format!("djanho-watch-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let theme = dir.join("theme.json");
+        let include = dir.join("include.json");
+        fs::write(&theme, "{}").unwrap();
+        fs::write(&include, "{}").unwrap();
+        let touch = |path: &Path, secs| {
+            let file = OpenOptions::new().write(true).open(path).unwrap();
+            file.set_modified(SystemTime::UNIX_EPOCH + Duration::from_secs(secs))
+                .unwrap();
+        };
+
+        let mut results = vec![
+            Ok(vec![theme.clone(), include.clone()]),
+            Err(vec![theme.clone()]),
+            Ok(vec![theme.clone()]),
+        ]
+        .into_iter();
+        let mut regenerate = || results.next().unwrap();
+        let mut watcher = Watcher::new(&mut regenerate);
+        assert!(!watcher.poll(&mut regenerate));
+
+        // The include is still watched after the theme failed to regenerate
+        touch(&include, 1);
+        assert!(watcher.poll(&mut regenerate));
+        assert_eq!(watcher.files, vec![theme.clone(), include.clone()]);
+        touch(&include, 2);
+        assert!(watcher.poll(&mut regenerate));
+        assert_eq!(watcher.files, vec![theme]);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,25 @@
+-- Loads the colorscheme named by the second argument from the runtime
+-- directory given as the first one, and prints the attributes of every
+-- highlight group as JSON, with colors as hex strings. Run with
+-- nvim --clean -l dump_highlights.lua dir name
+local keys = { 'fg', 'bg', 'sp', 'link', 'bold', 'italic', 'underline', 'undercurl', 'strikethrough', 'reverse' }
+
+vim.opt.runtimepath:prepend(arg[1])
+vim.cmd.colorscheme(arg[2])
+
+local groups = {}
+for group, attrs in pairs(vim.api.nvim_get_hl(0, {})) do
+    local dump = {}
+    for _, key in ipairs(keys) do
+        local value = attrs[key]
+        if type(value) == 'number' then
+            value = string.format('#%06x', value)
+        end
+        dump[key] = value
+    end
+    if next(dump) then
+        groups[group] = dump
+    end
+end
+
+io.write(vim.json.encode(groups))
@@ -0,0 +1,262 @@
+//! Converts a corpus of themes and, when Neovim is installed, loads every kind
+//! of colorscheme with `nvim --headless` and compares the resulting highlights
+//! against the snapshots in `tests/snapshots`.
+//!
+//! The themes in `tests/themes` are trimmed copies of Dracula, Solarized
+//! Dark, Gruvbox Dark, One Dark Pro and GitHub Light, keeping their palettes
+//! and the rules that color the most groups.
+//!
+//! Set `DJANHO_UPDATE_SNAPSHOTS=1` to rewrite the snapshots from Neovim.
+
+use std::{
+    collections::{BTreeMap, HashSet},
+    env, fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use djanho::{convert_with, ir::ResolvedTheme, Options, OptionsBuilder, Target, Theme};
+use serde_json::{Map, Value};
+
+const THEMES: &[&str] = &[
+    "dracula",
+    "solarized-dark",
+    "gruvbox-dark",
+    "one-dark",
+    "github-light",
+];
+
+/// Attributes the text styles of a group are made of
+const STYLES: &[&str] = &[
+    "bold",
+    "italic",
+    "underline",
+    "undercurl",
+    "strikethrough",
+    "reverse",
+];
+
+/// Every kind of colorscheme that sets the highlights globally
+fn targets() -> Vec<(&'static str, Target, OptionsBuilder)> {
+    vec![
+        ("vimscript", Target::Vimscript, Options::builder()),
+        ("lua", Target::Lua, Options::builder()),
+        ("compiled", Target::Lua, Options::builder().compiled(true)),
+        ("module", Target::Lua, Options::builder().lua_module(true)),
+    ]
+}
+
+fn fixture(path: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join(path)
+}
+
+fn theme(name: &str) -> Theme {
+    Theme::from_path(fixture(&format!("themes/{}.json", name))).unwrap()
+}
+
+fn snapshot(name: &str) -> BTreeMap<String, Value> {
+    let path = fixture(&format!("snapshots/{}.json", name));
+    serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap()
+}
+
+/// Groups set by the colorscheme, as the rest keep Neovim's defaults
+fn groups(theme: &ResolvedTheme) -> HashSet<String> {
+    theme
+        .highlights
+        .iter()
+        .map(|highlight| highlight.group.clone())
+        .chain(theme.links.iter().map(|link| link.group.clone()))
+        .collect()
+}
+
+/// The highlights `nvim_set_hl` ends up with for the compiled colorscheme,
+/// where later definitions of a group replace the attributes they set and
+/// links replace the whole group
+fn compiled_highlights(theme: &ResolvedTheme) -> BTreeMap<String, Value> {
+    let color = |name: &String| {
+        theme
+            .palette
+            .iter()
+            .find(|color| &color.name == name)
+            .map(|color| color.color.clone())
+            .filter(|color| color != "NONE")
+    };
+
+    let mut groups: BTreeMap<String, Map<String, Value>> = BTreeMap::new();
+    for highlight in &theme.highlights {
+        let attrs = groups.entry(highlight.group.clone()).or_default();
+        let colors = [
+            ("fg", &highlight.foreground),
+            ("bg", &highlight.background),
+            ("sp", &highlight.special),
+        ];
+        for (key, name) in colors.iter() {
+            if let Some(name) = name {
+                match color(name) {
+                    Some(color) => attrs.insert(key.to_string(), Value::from(color)),
+                    None => attrs.remove(*key),
+                };
+            }
+        }
+        if let Some(style) = &highlight.text_style {
+            for key in STYLES {
+                attrs.remove(*key);
+            }
+            for key in style.split(',').filter(|key| *key != "NONE") {
+                attrs.insert(key.to_string(), Value::from(true));
+            }
+        }
+    }
+    for link in &theme.links {
+        let mut attrs = Map::new();
+        attrs.insert("link".to_string(), Value::from(link.target.clone()));
+        groups.insert(link.group.clone(), attrs);
+    }
+
+    groups
+        .into_iter()
+        .filter(|(_, attrs)| !attrs.is_empty())
+        .map(|(group, attrs)| (group, Value::Object(attrs)))
+        .collect()
+}
+
+fn has_nvim() -> bool {
+    Command::new("nvim")
+        .arg("--version")
+        .output()
+        .map(|output| output.status.success())
+        .unwrap_or(false)
+}
+
+/// Loads the colorscheme `name` out of the runtime directory `dir` in a
+/// headless Neovim, returning its highlights
+fn nvim_highlights(dir: &Path, name: &str) -> BTreeMap<String, Value> {
+    let output = Command::new("nvim")
+        .arg("--clean")
+        .arg("-l")
+        .arg(fixture("dump_highlights.lua"))
+        .arg(dir)
+        .arg(name)
+        .output()
+        .unwrap();
+
+    assert!(
+        output.status.success(),
+        "Neovim couldn't load {}: {}",
+        dir.display(),
+        String::from_utf8_lossy(&output.stderr)
+    );
+    serde_json::from_slice(&output.stdout).unwrap()
+}
+
+#[test]
+fn can_convert_corpus() {
+    let builders = [
+        Options::builder(),
+        Options::builder().compiled(true),
+        Options::builder().lua_module(true),
+        Options::builder().namespace(true),
+        Options::builder().invert_variant(true),
+        Options::builder().transparent(true),
+    ];
+    for name in THEMES {
+        let theme = theme(name);
+        for builder in builders.iter() {
+            let options = builder.clone().name(*name).build();
+            for target in [Target::Vimscript, Target::Lua].iter() {
+                let output = convert_with(&theme, *target, &options);
+                assert!(output.is_ok(), "{}: {:?}", name, output);
+            }
+        }
+    }
+}
+
+#[test]
+fn snapshots_match_the_corpus() {
+    for name in THEMES {
+        let expected = compiled_highlights(&theme(name).resolve());
+        let snapshot = snapshot(name);
+        for (group, attrs) in &snapshot {
+            assert_eq!(
+                expected.get(group),
+                Some(attrs),
+                "{}: {} differs from the snapshot",
+                name,
+                group
+            );
+        }
+        assert_eq!(expected.len(), snapshot.len(), "{}: groups differ", name);
+    }
+}
+
+#[test]
+fn can_load_corpus_in_neovim() {
+    if !has_nvim() {
+        eprintln!("nvim isn't installed, skipping");
+        return;
+    }
+
+    let update = env::var_os("DJANHO_UPDATE_SNAPSHOTS").is_some();
+    for name in THEMES {
+        let theme = theme(name);
+        let groups = groups(&theme.resolve());
+        let snapshot_path = fixture(&format!("snapshots/{}.json", name));
+        let snapshot = if update {
+            BTreeMap::new()
+        } else {
+            snapshot(name)
+        };
+
+        for (kind, target, builder) in targets() {
+            let options = builder.name(*name).build();
+            let output = convert_with(&theme, target, &options).unwrap();
+
+            // Single files are colorschemes, while modules come with theirs
+            let dir =
+                env::temp_dir().join(format!("djanho-{}-{}-{}", name, kind, std::process::id()));
+            for file in &output.files {
+                let path = match output.files.len() {
+                    1 => dir.join("colors").join(&file.path),
+                    _ => dir.join(&file.path),
+                };
+                fs::create_dir_all(path.parent().unwrap()).unwrap();
+                fs::write(path, &file.contents).unwrap();
+            }
+            let mut highlights = nvim_highlights(&dir, name);
+            fs::remove_dir_all(&dir).unwrap();
+            highlights.retain(|group, _| groups.contains(group));
+
+            if kind == "compiled" {
+                if update {
+                    let json = serde_json::to_string_pretty(&highlights).unwrap();
+                    fs::write(&snapshot_path, json + "\n").unwrap();
+                } else {
+                    assert_eq!(highlights, snapshot, "{}: {} differs", name, kind);
+                }
+                continue;
+            }
+            if update {
+                continue;
+            }
+
+            // `:highlight` keeps the attributes of Neovim's default colors
+            // that the theme doesn't set, so only those it sets are compared
+            for (group, expected) in &snapshot {
+                let actual = highlights.get(group).and_then(Value::as_object);
+                for (key, value) in expected.as_object().unwrap() {
+                    assert_eq!(
+                        actual.and_then(|attrs| attrs.get(key)),
+                        Some(value),
+                        "{}: {} {} of {} differs from the snapshot",
+                        name,
+                        kind,
+                        key,
+                        group
+                    );
+                }
+            }
+        }
+    }
+}